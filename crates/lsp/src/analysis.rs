@@ -0,0 +1,246 @@
+//! Everything the language server knows about a single source file.
+
+use std::{collections::HashMap, io, path::Path};
+
+use azuki_syntax::{
+    ast::*,
+    diagnostics::{Diagnostic, Level, LineCol},
+    scope::{DefId, Resolution, Resolver},
+    source_map::SourceMap,
+    span::{FileId, Span},
+    visitor::AstVisitor,
+};
+use azuki_tac::Intrinsic;
+use azuki_tacgen::{builtins, err::SourceTy};
+use lsp_types::{DiagnosticSeverity, Position, Range};
+
+/// The analysis of a source file, computed once every time it changes.
+pub struct Analysis {
+    files: SourceMap,
+    main: FileId,
+    /// The programs of all files, with the analyzed file being the last one
+    programs: Vec<Program>,
+    resolution: Resolution,
+    /// Hover text of every definition
+    signatures: HashMap<DefId, String>,
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl Analysis {
+    /// Analyzes `src`, which is the content of the file at `path`. Imported
+    /// files are read from disk, which needs the path to be known.
+    pub fn new(src: String, path: Option<&Path>) -> Analysis {
+        let mut files = SourceMap::new();
+        let name = path.map_or_else(|| "untitled".into(), |p| p.to_string_lossy());
+        let main = files.add(name, src);
+        let (programs, parse_errors) = files.parse_with_imports(main, |import| match path {
+            Some(_) => std::fs::read_to_string(import),
+            None => Err(io::Error::new(
+                io::ErrorKind::NotFound,
+                "imports of unsaved files can't be found",
+            )),
+        });
+        let mut diagnostics: Vec<_> = parse_errors.iter().map(|e| e.to_diagnostic()).collect();
+
+        let mut resolver = Resolver::new();
+        let mut signatures = HashMap::new();
+        for &intrinsic in Intrinsic::ALL.iter() {
+            let id = resolver.declare_builtin(intrinsic.name());
+            let ty = builtins::ty_of(intrinsic);
+            signatures.insert(id, format!("{}: {}", intrinsic.name(), SourceTy(&ty)));
+        }
+        let resolution = resolver.resolve_all(&programs);
+
+        let mut collector = SignatureCollector::default();
+        for program in &programs {
+            collector.visit_program(program);
+        }
+        signatures.extend(
+            collector
+                .signatures
+                .into_iter()
+                .filter_map(|(span, sig)| Some((resolution.def_at(span)?, sig))),
+        );
+
+        // Code generation can't make sense of a program with syntax errors
+        if diagnostics.is_empty() {
+            if let Err(errors) = azuki_tacgen::compile_files(&programs) {
+                diagnostics.extend(errors.iter().map(|e| e.to_diagnostic()));
+            }
+        }
+        // Code generation already reports the hard errors
+        diagnostics.extend(
+            resolution
+                .errors
+                .iter()
+                .filter(|e| !e.is_error())
+                .map(|e| e.to_diagnostic()),
+        );
+        // Problems inside imported files are reported when they are opened
+        diagnostics.retain(|diag| diag.span.is_none_or(|span| span.file == main));
+
+        Analysis {
+            files,
+            main,
+            programs,
+            resolution,
+            signatures,
+            diagnostics,
+        }
+    }
+
+    pub fn diagnostics(&self) -> Vec<lsp_types::Diagnostic> {
+        self.diagnostics
+            .iter()
+            .map(|diag| {
+                let severity = match diag.level {
+                    Level::Error => DiagnosticSeverity::ERROR,
+                    Level::Warning => DiagnosticSeverity::WARNING,
+                    Level::Note => DiagnosticSeverity::INFORMATION,
+                };
+                let message = match &diag.label {
+                    Some(label) => format!("{}: {}", diag.message, label),
+                    None => diag.message.clone(),
+                };
+                let range = diag
+                    .span
+                    .map_or_else(Range::default, |span| self.range(span));
+                lsp_types::Diagnostic {
+                    range,
+                    severity: Some(severity),
+                    source: Some("azuki".into()),
+                    message,
+                    ..Default::default()
+                }
+            })
+            .collect()
+    }
+
+    /// Returns the span of the identifier at `pos` and the signature of its
+    /// definition.
+    pub fn hover(&self, pos: usize) -> Option<(Span, &str)> {
+        let (span, def) = self.resolution.ident_at(self.main, pos)?;
+        Some((span, self.signatures.get(&def)?))
+    }
+
+    /// Returns the span of the name that the identifier at `pos` refers to,
+    /// which may be inside an imported file. Builtin functions have no
+    /// definition to go to.
+    pub fn definition(&self, pos: usize) -> Option<Span> {
+        let (_, def) = self.resolution.ident_at(self.main, pos)?;
+        self.resolution.def(def).span
+    }
+
+    /// Returns the functions in this file, with their signatures.
+    pub fn functions(&self) -> impl Iterator<Item = (&FuncStmt, &str)> {
+        let program = self
+            .programs
+            .last()
+            .expect("The analyzed file is always parsed");
+        program.funcs.iter().map(move |func| {
+            let sig = self
+                .resolution
+                .def_at(func.name.span)
+                .and_then(|def| self.signatures.get(&def))
+                .map_or("", |sig| sig.as_str());
+            (func, sig)
+        })
+    }
+
+    /// Returns the path of the file the span is in, or `None` if it's in the
+    /// analyzed file.
+    pub fn path_of(&self, span: Span) -> Option<&Path> {
+        if span.file == self.main {
+            None
+        } else {
+            Some(Path::new(&self.files.file(span.file).name))
+        }
+    }
+
+    pub fn range(&self, span: Span) -> Range {
+        Range::new(
+            self.position(span.file, span.start()),
+            self.position(span.file, span.end()),
+        )
+    }
+
+    // LSP counts columns in UTF-16 code units, which is the same as counting
+    // characters outside of the astral planes.
+    fn position(&self, file: FileId, idx: usize) -> Position {
+        let file = self.files.file(file);
+        let LineCol { line, col } = file.lines.line_col(&file.src, idx);
+        Position::new(line as u32, col as u32)
+    }
+
+    /// Maps a position inside the analyzed file into a byte offset.
+    pub fn offset(&self, pos: Position) -> usize {
+        let pos = LineCol {
+            line: pos.line as usize,
+            col: pos.character as usize,
+        };
+        let file = self.files.file(self.main);
+        file.lines.offset_of(&file.src, pos)
+    }
+}
+
+/// Collects the signatures of declared names, keyed by the spans of the names.
+#[derive(Default)]
+struct SignatureCollector {
+    signatures: HashMap<Span, String>,
+}
+
+impl AstVisitor for SignatureCollector {
+    type LExprResult = ();
+    type ExprResult = ();
+    type TyResult = ();
+    type StmtResult = ();
+    type ProgramResult = ();
+    type FuncResult = ();
+
+    fn default_lexpr_result() {}
+    fn default_expr_result() {}
+    fn default_ty_result() {}
+    fn default_stmt_result() {}
+    fn default_program_result() {}
+    fn default_func_result() {}
+
+    fn visit_func(&mut self, func: &FuncStmt) -> Self::FuncResult {
+        let sig = fn_signature(&func.name, &func.params, &func.ret_ty);
+        self.signatures.insert(func.name.span, sig);
+        for param in &func.params {
+            self.visit_func_param(param);
+        }
+        self.visit_block_stmt(&func.body);
+    }
+
+    fn visit_extern_func(&mut self, func: &ExternFuncStmt) -> Self::FuncResult {
+        let sig = fn_signature(&func.name, &func.params, &func.ret_ty);
+        self.signatures
+            .insert(func.name.span, format!("extern {}", sig));
+    }
+
+    fn visit_func_param(&mut self, param: &FuncParam) -> Self::StmtResult {
+        self.signatures
+            .insert(param.name.span, param_signature(param));
+    }
+
+    fn visit_decl_stmt(&mut self, stmt: &DeclStmt) -> Self::StmtResult {
+        let kw = if stmt.is_const { "const" } else { "let" };
+        let sig = format!("{} {}: {}", kw, stmt.name.name, stmt.ty);
+        self.signatures.insert(stmt.name.span, sig);
+    }
+}
+
+fn fn_signature(name: &Ident, params: &[FuncParam], ret_ty: &TyDef) -> String {
+    let params = params
+        .iter()
+        .map(param_signature)
+        .collect::<Vec<_>>()
+        .join(", ");
+    format!("fn {}({}) -> {}", name.name, params, ret_ty)
+}
+
+fn param_signature(param: &FuncParam) -> String {
+    let kw = if param.is_const { "const " } else { "" };
+    format!("{}{}: {}", kw, param.name.name, param.ty)
+}
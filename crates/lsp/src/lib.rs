@@ -0,0 +1,199 @@
+//! A language server for C0, speaking LSP through an [`lsp_server::Connection`].
+//!
+//! Supported features are diagnostics, hover, go-to-definition and document
+//! symbols. Documents are synchronized in full and analyzed again on every
+//! change.
+
+pub mod analysis;
+mod test;
+
+use std::{collections::HashMap, error::Error};
+
+use analysis::Analysis;
+use lsp_server::{Connection, ErrorCode, Message, Notification, Request, Response};
+use lsp_types::{
+    notification::{
+        DidChangeTextDocument, DidCloseTextDocument, DidOpenTextDocument,
+        Notification as LspNotification, PublishDiagnostics,
+    },
+    request::{DocumentSymbolRequest, GotoDefinition, HoverRequest, Request as LspRequest},
+    DocumentSymbol, DocumentSymbolParams, DocumentSymbolResponse, GotoDefinitionParams,
+    GotoDefinitionResponse, Hover, HoverContents, HoverParams, HoverProviderCapability, Location,
+    MarkupContent, MarkupKind, OneOf, PublishDiagnosticsParams, ServerCapabilities, SymbolKind,
+    TextDocumentSyncCapability, TextDocumentSyncKind, Url,
+};
+
+pub type Result<T> = std::result::Result<T, Box<dyn Error + Send + Sync>>;
+
+pub fn capabilities() -> ServerCapabilities {
+    ServerCapabilities {
+        text_document_sync: Some(TextDocumentSyncCapability::Kind(TextDocumentSyncKind::FULL)),
+        hover_provider: Some(HoverProviderCapability::Simple(true)),
+        definition_provider: Some(OneOf::Left(true)),
+        document_symbol_provider: Some(OneOf::Left(true)),
+        ..Default::default()
+    }
+}
+
+/// Runs the language server until the client asks it to shut down.
+pub fn run(connection: &Connection) -> Result<()> {
+    connection.initialize(serde_json::to_value(capabilities())?)?;
+    let mut server = Server {
+        connection,
+        docs: HashMap::new(),
+    };
+    for msg in &connection.receiver {
+        match msg {
+            Message::Request(req) => {
+                if connection.handle_shutdown(&req)? {
+                    return Ok(());
+                }
+                server.handle_request(req)?;
+            }
+            Message::Notification(not) => server.handle_notification(not)?,
+            Message::Response(_) => {}
+        }
+    }
+    Ok(())
+}
+
+struct Server<'a> {
+    connection: &'a Connection,
+    docs: HashMap<Url, Analysis>,
+}
+
+impl<'a> Server<'a> {
+    fn handle_request(&mut self, req: Request) -> Result<()> {
+        let resp = match req.method.as_str() {
+            HoverRequest::METHOD => self.respond::<HoverRequest>(req, Self::hover),
+            GotoDefinition::METHOD => self.respond::<GotoDefinition>(req, Self::definition),
+            DocumentSymbolRequest::METHOD => {
+                self.respond::<DocumentSymbolRequest>(req, Self::document_symbols)
+            }
+            _ => Response::new_err(
+                req.id,
+                ErrorCode::MethodNotFound as i32,
+                format!("unknown request `{}`", req.method),
+            ),
+        };
+        self.connection.sender.send(resp.into())?;
+        Ok(())
+    }
+
+    fn respond<R: LspRequest>(
+        &self,
+        req: Request,
+        f: fn(&Self, R::Params) -> R::Result,
+    ) -> Response {
+        match serde_json::from_value(req.params) {
+            Ok(params) => Response::new_ok(req.id, f(self, params)),
+            Err(e) => Response::new_err(req.id, ErrorCode::InvalidParams as i32, e.to_string()),
+        }
+    }
+
+    fn handle_notification(&mut self, not: Notification) -> Result<()> {
+        match not.method.as_str() {
+            DidOpenTextDocument::METHOD => {
+                let params: <DidOpenTextDocument as LspNotification>::Params =
+                    serde_json::from_value(not.params)?;
+                let doc = params.text_document;
+                self.update(doc.uri, doc.text, Some(doc.version))
+            }
+            DidChangeTextDocument::METHOD => {
+                let params: <DidChangeTextDocument as LspNotification>::Params =
+                    serde_json::from_value(not.params)?;
+                // Only full synchronization is supported, so the last change
+                // holds the whole document
+                match params.content_changes.into_iter().last() {
+                    Some(change) => self.update(
+                        params.text_document.uri,
+                        change.text,
+                        Some(params.text_document.version),
+                    ),
+                    None => Ok(()),
+                }
+            }
+            DidCloseTextDocument::METHOD => {
+                let params: <DidCloseTextDocument as LspNotification>::Params =
+                    serde_json::from_value(not.params)?;
+                let uri = params.text_document.uri;
+                self.docs.remove(&uri);
+                self.publish_diagnostics(uri, vec![], None)
+            }
+            _ => Ok(()),
+        }
+    }
+
+    fn update(&mut self, uri: Url, text: String, version: Option<i32>) -> Result<()> {
+        let path = uri.to_file_path().ok();
+        let analysis = Analysis::new(text, path.as_deref());
+        let diagnostics = analysis.diagnostics();
+        self.docs.insert(uri.clone(), analysis);
+        self.publish_diagnostics(uri, diagnostics, version)
+    }
+
+    fn publish_diagnostics(
+        &self,
+        uri: Url,
+        diagnostics: Vec<lsp_types::Diagnostic>,
+        version: Option<i32>,
+    ) -> Result<()> {
+        let params = PublishDiagnosticsParams {
+            uri,
+            diagnostics,
+            version,
+        };
+        let not = Notification::new(PublishDiagnostics::METHOD.into(), params);
+        self.connection.sender.send(not.into())?;
+        Ok(())
+    }
+
+    fn hover(&self, params: HoverParams) -> Option<Hover> {
+        let pos = params.text_document_position_params;
+        let doc = self.docs.get(&pos.text_document.uri)?;
+        let (span, sig) = doc.hover(doc.offset(pos.position))?;
+        Some(Hover {
+            contents: HoverContents::Markup(MarkupContent {
+                kind: MarkupKind::Markdown,
+                value: format!("```c0\n{}\n```", sig),
+            }),
+            range: Some(doc.range(span)),
+        })
+    }
+
+    fn definition(&self, params: GotoDefinitionParams) -> Option<GotoDefinitionResponse> {
+        let pos = params.text_document_position_params;
+        let doc = self.docs.get(&pos.text_document.uri)?;
+        let span = doc.definition(doc.offset(pos.position))?;
+        let uri = match doc.path_of(span) {
+            Some(path) => Url::from_file_path(path).ok()?,
+            None => pos.text_document.uri,
+        };
+        Some(GotoDefinitionResponse::Scalar(Location::new(
+            uri,
+            doc.range(span),
+        )))
+    }
+
+    fn document_symbols(&self, params: DocumentSymbolParams) -> Option<DocumentSymbolResponse> {
+        let doc = self.docs.get(&params.text_document.uri)?;
+        let symbols = doc
+            .functions()
+            .map(|(func, sig)| {
+                // `deprecated` is deprecated, but it still has to be filled in
+                #[allow(deprecated)]
+                DocumentSymbol {
+                    name: func.name.name.to_string(),
+                    detail: Some(sig.into()),
+                    kind: SymbolKind::FUNCTION,
+                    tags: None,
+                    deprecated: None,
+                    range: doc.range(func.span),
+                    selection_range: doc.range(func.name.span),
+                    children: None,
+                }
+            })
+            .collect();
+        Some(DocumentSymbolResponse::Nested(symbols))
+    }
+}
@@ -0,0 +1,11 @@
+use lsp_server::Connection;
+
+fn main() -> azuki_lsp::Result<()> {
+    // Anything written to stdout goes to the client, so logs go to stderr
+    eprintln!("azuki-lsp: starting on stdio");
+    let (connection, io_threads) = Connection::stdio();
+    azuki_lsp::run(&connection)?;
+    drop(connection);
+    io_threads.join()?;
+    Ok(())
+}
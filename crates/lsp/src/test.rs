@@ -0,0 +1,185 @@
+#![cfg(test)]
+
+use std::thread::JoinHandle;
+
+use lsp_server::{Connection, Message, Notification, Request, RequestId};
+use serde_json::{json, Value};
+
+/// A scripted client talking to a server running in another thread.
+struct Client {
+    conn: Connection,
+    server: Option<JoinHandle<()>>,
+    next_id: i32,
+}
+
+impl Client {
+    fn start() -> Client {
+        let (server_conn, conn) = Connection::memory();
+        let server = std::thread::spawn(move || crate::run(&server_conn).unwrap());
+        let mut client = Client {
+            conn,
+            server: Some(server),
+            next_id: 0,
+        };
+        let caps = client.request("initialize", json!({ "capabilities": {} }));
+        assert_eq!(caps["capabilities"]["hoverProvider"], json!(true));
+        client.notify("initialized", json!({}));
+        client
+    }
+
+    fn request(&mut self, method: &str, params: Value) -> Value {
+        self.next_id += 1;
+        let id = RequestId::from(self.next_id);
+        let req = Request::new(id.clone(), method.into(), params);
+        self.conn.sender.send(req.into()).unwrap();
+        match self.conn.receiver.recv().unwrap() {
+            Message::Response(resp) => {
+                assert_eq!(resp.id, id);
+                assert!(resp.error.is_none(), "{:?}", resp.error);
+                resp.result.unwrap_or(Value::Null)
+            }
+            msg => panic!("expected a response, got {:?}", msg),
+        }
+    }
+
+    fn notify(&self, method: &str, params: Value) {
+        let not = Notification::new(method.into(), params);
+        self.conn.sender.send(not.into()).unwrap();
+    }
+
+    fn recv_notification(&self) -> Notification {
+        match self.conn.receiver.recv().unwrap() {
+            Message::Notification(not) => not,
+            msg => panic!("expected a notification, got {:?}", msg),
+        }
+    }
+
+    fn shutdown(mut self) {
+        self.request("shutdown", Value::Null);
+        self.notify("exit", Value::Null);
+        self.server.take().unwrap().join().unwrap();
+    }
+}
+
+fn position(line: u32, character: u32) -> Value {
+    json!({
+        "textDocument": { "uri": URI },
+        "position": { "line": line, "character": character },
+    })
+}
+
+const URI: &str = "file:///main.c0";
+
+#[test]
+fn scripted_session() {
+    let mut client = Client::start();
+
+    let src = "fn main() -> void {\n    let x: int = 1;\n    putint(x + y);\n}\n";
+    client.notify(
+        "textDocument/didOpen",
+        json!({
+            "textDocument": { "uri": URI, "languageId": "c0", "version": 1, "text": src }
+        }),
+    );
+    let not = client.recv_notification();
+    assert_eq!(not.method, "textDocument/publishDiagnostics");
+    let diags = &not.params["diagnostics"];
+    assert_eq!(diags.as_array().unwrap().len(), 1);
+    assert_eq!(diags[0]["message"], "cannot find `y` in this scope");
+    assert_eq!(
+        diags[0]["range"],
+        json!({ "start": { "line": 2, "character": 15 }, "end": { "line": 2, "character": 16 } })
+    );
+
+    let src = "fn main() -> void {\n    let x: int = 1;\n    putint(f(x));\n}\n\
+               fn f(a: int) -> int { return a; }\n";
+    client.notify(
+        "textDocument/didChange",
+        json!({
+            "textDocument": { "uri": URI, "version": 2 },
+            "contentChanges": [{ "text": src }],
+        }),
+    );
+    let not = client.recv_notification();
+    assert_eq!(not.params["diagnostics"], json!([]));
+    assert_eq!(not.params["version"], json!(2));
+
+    // Hovering over the use of `x`
+    let hover = client.request("textDocument/hover", position(2, 13));
+    assert_eq!(hover["contents"]["value"], "```c0\nlet x: int\n```");
+    let hover = client.request("textDocument/hover", position(2, 5));
+    assert_eq!(
+        hover["contents"]["value"],
+        "```c0\nputint: fn(int) -> void\n```"
+    );
+
+    // Going to the definition of `f`
+    let def = client.request("textDocument/definition", position(2, 11));
+    assert_eq!(
+        def,
+        json!({
+            "uri": URI,
+            "range": { "start": { "line": 4, "character": 3 }, "end": { "line": 4, "character": 4 } },
+        })
+    );
+    // Builtins have nowhere to go
+    let def = client.request("textDocument/definition", position(2, 5));
+    assert_eq!(def, Value::Null);
+
+    let symbols = client.request(
+        "textDocument/documentSymbol",
+        json!({ "textDocument": { "uri": URI } }),
+    );
+    let names: Vec<_> = symbols
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|sym| (sym["name"].clone(), sym["detail"].clone()))
+        .collect();
+    assert_eq!(
+        names,
+        vec![
+            (json!("main"), json!("fn main() -> void")),
+            (json!("f"), json!("fn f(a: int) -> int")),
+        ]
+    );
+
+    client.shutdown();
+}
+
+#[test]
+fn imports_from_disk() {
+    let dir = std::env::temp_dir().join(format!("azuki-lsp-test-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let lib = dir.join("lib.c0");
+    std::fs::write(&lib, "fn twice(x: int) -> int { return x * 2; }\n").unwrap();
+    let uri = lsp_types::Url::from_file_path(dir.join("main.c0")).unwrap();
+
+    let mut client = Client::start();
+    let src = "import \"lib.c0\";\nfn main() -> void { putint(twice(2)); }\n";
+    client.notify(
+        "textDocument/didOpen",
+        json!({
+            "textDocument": { "uri": uri, "languageId": "c0", "version": 1, "text": src }
+        }),
+    );
+    let not = client.recv_notification();
+    assert_eq!(not.params["diagnostics"], json!([]));
+
+    let def = client.request(
+        "textDocument/definition",
+        json!({
+            "textDocument": { "uri": uri },
+            "position": { "line": 1, "character": 28 },
+        }),
+    );
+    let lib_uri = lsp_types::Url::from_file_path(&lib).unwrap();
+    assert_eq!(def["uri"], json!(lib_uri));
+    assert_eq!(
+        def["range"],
+        json!({ "start": { "line": 0, "character": 3 }, "end": { "line": 0, "character": 8 } })
+    );
+
+    client.shutdown();
+    std::fs::remove_dir_all(&dir).unwrap();
+}
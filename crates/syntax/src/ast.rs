@@ -12,10 +12,21 @@ use smol_str::SmolStr;
 #[derive(Debug, Clone)]
 #[cfg_attr(feature = "serde_impl", derive(Serialize, Deserialize))]
 pub struct Program {
+    pub imports: Vec<ImportStmt>,
     pub decls: Vec<DeclStmt>,
+    pub externs: Vec<ExternFuncStmt>,
     pub funcs: Vec<FuncStmt>,
 }
 
+/// `import "path";`, which brings the global items of another file into
+/// scope. The path is relative to the importing file.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde_impl", derive(Serialize, Deserialize))]
+pub struct ImportStmt {
+    pub span: Span,
+    pub path: String,
+}
+
 pub trait AstNode {
     fn span(&self) -> Span;
 }
@@ -30,6 +41,17 @@ pub struct FuncStmt {
     pub body: BlockStmt,
 }
 
+/// `extern fn name(params) -> ty;`, a function without a body. Its
+/// implementation is provided by the host running the program.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde_impl", derive(Serialize, Deserialize))]
+pub struct ExternFuncStmt {
+    pub span: Span,
+    pub name: Ident,
+    pub params: Vec<FuncParam>,
+    pub ret_ty: TyDef,
+}
+
 #[derive(Debug, Clone)]
 #[cfg_attr(feature = "serde_impl", derive(Serialize, Deserialize))]
 pub struct FuncParam {
@@ -43,6 +65,7 @@ pub struct FuncParam {
 pub enum Stmt {
     Block(BlockStmt),
     While(WhileStmt),
+    For(ForStmt),
     If(IfStmt),
     Expr(Expr),
     Decl(DeclStmt),
@@ -50,6 +73,8 @@ pub enum Stmt {
     Break(Span),
     Continue(Span),
     Empty(Span),
+    /// A placeholder for statements that failed to parse.
+    Error(Span),
 }
 
 impl Stmt {
@@ -57,6 +82,7 @@ impl Stmt {
         match self {
             Stmt::Block(i) => i.span,
             Stmt::While(i) => i.span,
+            Stmt::For(i) => i.span,
             Stmt::If(i) => i.span,
             Stmt::Expr(i) => i.span(),
             Stmt::Decl(i) => i.span,
@@ -64,6 +90,7 @@ impl Stmt {
             Stmt::Break(s) => *s,
             Stmt::Continue(s) => *s,
             Stmt::Empty(s) => *s,
+            Stmt::Error(s) => *s,
         }
     }
 }
@@ -89,8 +116,16 @@ pub struct ReturnStmt {
 #[cfg_attr(feature = "serde_impl", derive(Serialize, Deserialize))]
 pub struct TyDef {
     pub span: Span,
-    pub name: SmolStr,
-    pub params: Option<Vec<TyDef>>,
+    pub kind: TyDefKind,
+}
+
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde_impl", derive(Serialize, Deserialize))]
+pub enum TyDefKind {
+    /// A type referred by its name, e.g. `int`
+    Named(SmolStr),
+    /// An array type with fixed length, e.g. `[int; 10]`
+    Array { elem: P<TyDef>, len: u64 },
 }
 
 #[derive(Debug, Clone)]
@@ -108,6 +143,19 @@ pub struct WhileStmt {
     pub body: P<BlockStmt>,
 }
 
+/// A C-style for loop: `for (init; cond; step) { body }`. Every part inside
+/// the parentheses may be omitted. A missing condition is always true.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde_impl", derive(Serialize, Deserialize))]
+pub struct ForStmt {
+    pub span: Span,
+    /// Either a declaration or an expression statement
+    pub init: Option<P<Stmt>>,
+    pub cond: Option<P<Expr>>,
+    pub step: Option<P<Expr>>,
+    pub body: P<BlockStmt>,
+}
+
 #[derive(Debug, Clone)]
 #[cfg_attr(feature = "serde_impl", derive(Serialize, Deserialize))]
 pub struct IfStmt {
@@ -133,8 +181,10 @@ pub enum Expr {
     As(AsExpr),
     Literal(LiteralExpr),
     Unary(UnaryExpr),
+    IncDec(IncDecExpr),
     Binary(BinaryExpr),
     Call(CallExpr),
+    Index(IndexExpr),
 }
 
 impl Expr {
@@ -145,8 +195,10 @@ impl Expr {
             Expr::As(x) => x.span,
             Expr::Literal(x) => x.span,
             Expr::Unary(x) => x.span,
+            Expr::IncDec(x) => x.span,
             Expr::Binary(x) => x.span,
             Expr::Call(x) => x.span,
+            Expr::Index(x) => x.span,
         }
     }
 }
@@ -162,6 +214,7 @@ pub struct LiteralExpr {
 #[cfg_attr(feature = "serde_impl", derive(Serialize, Deserialize))]
 pub enum LiteralKind {
     Integer(u64),
+    Bool(bool),
     Float(f64),
     String(String),
     Char(char),
@@ -180,10 +233,25 @@ pub struct UnaryExpr {
 pub struct AssignExpr {
     pub span: Span,
     pub allow_assign_const: bool,
+    /// The operator of a compound assignment like `a += b`, or `None` for a
+    /// plain `a = b`
+    pub op: Option<BinaryOp>,
     pub lhs: P<Expr>,
     pub rhs: P<Expr>,
 }
 
+/// `++x`, `--x`, `x++` or `x--`
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde_impl", derive(Serialize, Deserialize))]
+pub struct IncDecExpr {
+    pub span: Span,
+    pub op: IncDecOp,
+    /// Prefix operators evaluate to the updated value, and postfix ones to the
+    /// value before updating
+    pub is_prefix: bool,
+    pub expr: P<Expr>,
+}
+
 #[derive(Debug, Clone)]
 #[cfg_attr(feature = "serde_impl", derive(Serialize, Deserialize))]
 pub struct AsExpr {
@@ -192,6 +260,15 @@ pub struct AsExpr {
     pub ty: TyDef,
 }
 
+/// An array index expression, e.g. `a[i]`
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde_impl", derive(Serialize, Deserialize))]
+pub struct IndexExpr {
+    pub span: Span,
+    pub base: P<Expr>,
+    pub index: P<Expr>,
+}
+
 #[derive(Debug, Clone)]
 #[cfg_attr(feature = "serde_impl", derive(Serialize, Deserialize))]
 pub struct BinaryExpr {
@@ -214,6 +291,15 @@ pub struct CallExpr {
 pub enum UnaryOp {
     Neg,
     Pos,
+    Not,
+    BitNot,
+}
+
+#[derive(Debug, Copy, Clone)]
+#[cfg_attr(feature = "serde_impl", derive(Serialize, Deserialize))]
+pub enum IncDecOp {
+    Inc,
+    Dec,
 }
 
 #[derive(Debug, Copy, Clone)]
@@ -223,12 +309,20 @@ pub enum BinaryOp {
     Sub,
     Mul,
     Div,
+    Mod,
+    BitAnd,
+    BitOr,
+    Xor,
+    Shl,
+    Shr,
     Gt,
     Lt,
     Ge,
     Le,
     Eq,
     Neq,
+    And,
+    Or,
 }
 
 #[derive(Debug, Clone)]
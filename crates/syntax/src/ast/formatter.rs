@@ -0,0 +1,834 @@
+//! Printing the AST back into source code, and dumping it as a tree.
+//!
+//! The source code printed by [`PrettyPrinter`] is canonical: parsing it again
+//! results in the same tree (spans aside), and printing that tree gives the
+//! same text. Parentheses are only emitted where precedence requires them.
+//!
+//! Comments are not part of the AST. To keep them while formatting a file, use
+//! [`PrettyPrinter::print_program_with_comments`] with the original source.
+
+use std::fmt::{Display, Formatter, Result, Write};
+
+use super::*;
+use crate::{
+    lexer::{lossless_lexer, LosslessToken},
+    Token,
+};
+
+/// Prints the AST as source code.
+#[derive(Debug, Clone)]
+pub struct PrettyPrinter {
+    indent: String,
+}
+
+impl Default for PrettyPrinter {
+    fn default() -> Self {
+        PrettyPrinter::new("    ")
+    }
+}
+
+impl PrettyPrinter {
+    /// Create a printer that indents every level with `indent`.
+    pub fn new(indent: impl Into<String>) -> Self {
+        PrettyPrinter {
+            indent: indent.into(),
+        }
+    }
+
+    pub fn print_program(&self, program: &Program) -> String {
+        let mut s = String::new();
+        self.writer(&mut s).write_program(program).unwrap();
+        s
+    }
+
+    /// Print `program`, which is parsed from `src`, keeping the comments inside
+    /// `src`.
+    ///
+    /// A comment at the end of a line stays after the statement or item on
+    /// that line, and other comments are placed on their own lines before the
+    /// next statement or item. Comments inside expressions are moved out of
+    /// them.
+    pub fn print_program_with_comments(&self, program: &Program, src: &str) -> String {
+        let comments = collect_comments(src);
+        let mut s = String::new();
+        let mut writer = self.writer(&mut s);
+        writer.comments = &comments;
+        writer.write_program(program).unwrap();
+        s
+    }
+
+    pub fn print_func(&self, func: &FuncStmt) -> String {
+        let mut s = String::new();
+        self.writer(&mut s).write_func(func).unwrap();
+        s
+    }
+
+    pub fn print_stmt(&self, stmt: &Stmt) -> String {
+        let mut s = String::new();
+        self.writer(&mut s).write_stmt(stmt).unwrap();
+        s
+    }
+
+    fn writer<'a>(&'a self, w: &'a mut dyn Write) -> SourceWriter<'a> {
+        SourceWriter {
+            indent: &self.indent,
+            level: 0,
+            w,
+            comments: &[],
+        }
+    }
+}
+
+struct SourceWriter<'a> {
+    indent: &'a str,
+    level: usize,
+    w: &'a mut dyn Write,
+    /// Comments that are not yet written, in source order
+    comments: &'a [Comment],
+}
+
+/// A comment inside the source code.
+struct Comment {
+    span: Span,
+    text: String,
+    /// Whether there's no code before this comment on the same line
+    own_line: bool,
+    /// The position of the code this comment trails, i.e. the start of the
+    /// last token before it, or the end of the token before a semicolon.
+    attach_pos: usize,
+}
+
+fn collect_comments(src: &str) -> Vec<Comment> {
+    let mut comments = vec![];
+    let mut own_line = true;
+    let mut attach_pos = 0;
+    let mut last_end = 0;
+    for (tok, span) in lossless_lexer(src) {
+        match tok {
+            LosslessToken::Whitespace => own_line |= src[span.start()..span.end()].contains('\n'),
+            LosslessToken::Comment => comments.push(Comment {
+                span,
+                text: src[span.start()..span.end()].trim_end().into(),
+                own_line,
+                attach_pos,
+            }),
+            LosslessToken::Token(_) | LosslessToken::Error(_) => {
+                own_line = false;
+                attach_pos = match tok {
+                    LosslessToken::Token(Token::Semicolon) => last_end,
+                    _ => span.start(),
+                };
+                last_end = span.end();
+            }
+        }
+    }
+    comments
+}
+
+/// A global item of a program.
+#[derive(Clone, Copy)]
+enum Item<'a> {
+    Import(&'a ImportStmt),
+    Decl(&'a DeclStmt),
+    Extern(&'a ExternFuncStmt),
+    Func(&'a FuncStmt),
+}
+
+impl<'a> Item<'a> {
+    fn span(&self) -> Span {
+        match self {
+            Item::Import(i) => i.span,
+            Item::Decl(i) => i.span,
+            Item::Extern(i) => i.span,
+            Item::Func(i) => i.span,
+        }
+    }
+    /// Whether no blank line is needed between `self` and the item `last`
+    /// before it. Imports, declarations and externs are grouped by their kinds,
+    /// and functions are always separated from other items.
+    fn groups_with(&self, last: Item) -> bool {
+        matches!(
+            (self, last),
+            (Item::Import(_), Item::Import(_))
+                | (Item::Decl(_), Item::Decl(_))
+                | (Item::Extern(_), Item::Extern(_))
+        )
+    }
+}
+
+/// Returns the items of `program` in the order they appear in the source code.
+/// `Program` keeps each kind of items in its own list, so the items are sorted
+/// by their spans. Items with the same span, e.g. ones built without source
+/// code, keep the order of the lists.
+fn items_in_source_order(program: &Program) -> Vec<Item<'_>> {
+    let mut items = program
+        .imports
+        .iter()
+        .map(Item::Import)
+        .chain(program.decls.iter().map(Item::Decl))
+        .chain(program.externs.iter().map(Item::Extern))
+        .chain(program.funcs.iter().map(Item::Func))
+        .collect::<Vec<_>>();
+    items.sort_by_key(|item| item.span().start());
+    items
+}
+
+/// Binding strength of expressions that are not binary operators. These bind
+/// tighter than any binary operator.
+const PREC_AS: u32 = 100;
+const PREC_UNARY: u32 = 101;
+const PREC_POSTFIX: u32 = 102;
+const PREC_ITEM: u32 = 103;
+
+/// Precedence of a binary operator, matching `Token::precedence`.
+fn binary_precedence(op: BinaryOp) -> u32 {
+    match op {
+        BinaryOp::Mul | BinaryOp::Div | BinaryOp::Mod => 20,
+        BinaryOp::Add | BinaryOp::Sub => 10,
+        BinaryOp::Shl | BinaryOp::Shr => 8,
+        BinaryOp::BitAnd => 7,
+        BinaryOp::Xor => 6,
+        BinaryOp::BitOr => 5,
+        BinaryOp::Gt
+        | BinaryOp::Lt
+        | BinaryOp::Ge
+        | BinaryOp::Le
+        | BinaryOp::Eq
+        | BinaryOp::Neq => 4,
+        BinaryOp::And => 3,
+        BinaryOp::Or => 2,
+    }
+}
+
+const PREC_ASSIGN: u32 = 1;
+
+fn precedence(expr: &Expr) -> u32 {
+    match expr {
+        Expr::Assign(_) => PREC_ASSIGN,
+        Expr::Binary(b) => binary_precedence(b.op),
+        Expr::As(_) => PREC_AS,
+        Expr::Unary(_) => PREC_UNARY,
+        Expr::IncDec(e) if e.is_prefix => PREC_UNARY,
+        Expr::IncDec(_) => PREC_POSTFIX,
+        Expr::Ident(_) | Expr::Literal(_) | Expr::Call(_) | Expr::Index(_) => PREC_ITEM,
+    }
+}
+
+impl<'a> SourceWriter<'a> {
+    fn write_indent(&mut self) -> Result {
+        for _ in 0..self.level {
+            self.w.write_str(self.indent)?;
+        }
+        Ok(())
+    }
+
+    /// Writes all comments before `pos` on their own lines.
+    fn write_comments_before(&mut self, pos: usize) -> Result {
+        while let Some((comment, rest)) = self.comments.split_first() {
+            if comment.span.start() >= pos {
+                break;
+            }
+            self.comments = rest;
+            self.write_indent()?;
+            writeln!(self.w, "{}", comment.text)?;
+        }
+        Ok(())
+    }
+
+    /// Writes the comment trailing the code at `span`, if there is one.
+    fn write_trailing_comment(&mut self, span: Span) -> Result {
+        if let Some((comment, rest)) = self.comments.split_first() {
+            if !comment.own_line
+                && comment.attach_pos >= span.start()
+                && comment.attach_pos <= span.end()
+            {
+                self.comments = rest;
+                write!(self.w, " {}", comment.text)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn write_program(&mut self, program: &Program) -> Result {
+        let mut last = None;
+        for item in items_in_source_order(program) {
+            if matches!(last, Some(last) if !item.groups_with(last)) {
+                writeln!(self.w)?;
+            }
+            last = Some(item);
+
+            self.write_comments_before(item.span().start())?;
+            match item {
+                Item::Import(import) => {
+                    self.write_indent()?;
+                    write!(self.w, "{}", import)?;
+                    self.write_trailing_comment(import.span)?;
+                    writeln!(self.w)?;
+                }
+                Item::Decl(decl) => {
+                    self.write_indent()?;
+                    self.write_decl(decl)?;
+                    self.write_trailing_comment(decl.span)?;
+                    writeln!(self.w)?;
+                }
+                Item::Extern(func) => self.write_extern_func(func)?,
+                Item::Func(func) => self.write_func(func)?,
+            }
+        }
+        self.write_comments_before(usize::MAX)
+    }
+
+    fn write_func(&mut self, func: &FuncStmt) -> Result {
+        self.write_indent()?;
+        self.write_fn_signature(&func.name, &func.params, &func.ret_ty)?;
+        write!(self.w, " ")?;
+        self.write_block(&func.body)?;
+        self.write_trailing_comment(func.span)?;
+        writeln!(self.w)
+    }
+
+    fn write_extern_func(&mut self, func: &ExternFuncStmt) -> Result {
+        self.write_indent()?;
+        write!(self.w, "extern ")?;
+        self.write_fn_signature(&func.name, &func.params, &func.ret_ty)?;
+        write!(self.w, ";")?;
+        self.write_trailing_comment(func.span)?;
+        writeln!(self.w)
+    }
+
+    /// Writes `fn name(params) -> ret_ty`.
+    fn write_fn_signature(&mut self, name: &Ident, params: &[FuncParam], ret_ty: &TyDef) -> Result {
+        write!(self.w, "fn {}(", name.name)?;
+        for (idx, param) in params.iter().enumerate() {
+            if idx != 0 {
+                write!(self.w, ", ")?;
+            }
+            if param.is_const {
+                write!(self.w, "const ")?;
+            }
+            write!(self.w, "{}: {}", param.name.name, param.ty)?;
+        }
+        write!(self.w, ") -> {}", ret_ty)
+    }
+
+    /// Writes a block, starting from the current position and ending right
+    /// after the closing brace.
+    fn write_block(&mut self, block: &BlockStmt) -> Result {
+        let has_comments = matches!(
+            self.comments.first(),
+            Some(comment) if comment.span.start() < block.span.end()
+        );
+        if block.stmts.is_empty() && !has_comments {
+            return write!(self.w, "{{}}");
+        }
+        writeln!(self.w, "{{")?;
+        self.level += 1;
+        for stmt in &block.stmts {
+            self.write_stmt(stmt)?;
+        }
+        self.write_comments_before(block.span.end())?;
+        self.level -= 1;
+        self.write_indent()?;
+        write!(self.w, "}}")
+    }
+
+    /// Writes a statement on its own line(s).
+    fn write_stmt(&mut self, stmt: &Stmt) -> Result {
+        self.write_comments_before(stmt.span().start())?;
+        self.write_indent()?;
+        match stmt {
+            Stmt::Block(b) => self.write_block(b)?,
+            Stmt::While(s) => {
+                write!(self.w, "while ")?;
+                self.write_expr(&s.cond)?;
+                write!(self.w, " ")?;
+                self.write_block(&s.body)?;
+            }
+            Stmt::For(s) => {
+                write!(self.w, "for (")?;
+                match s.init.as_deref() {
+                    Some(Stmt::Decl(decl)) => self.write_decl(decl)?,
+                    Some(Stmt::Expr(expr)) => {
+                        self.write_expr(expr)?;
+                        write!(self.w, ";")?;
+                    }
+                    Some(other) => unreachable!("Invalid for loop initializer {:?}", other),
+                    None => write!(self.w, ";")?,
+                }
+                if let Some(cond) = &s.cond {
+                    write!(self.w, " ")?;
+                    self.write_expr(cond)?;
+                }
+                write!(self.w, ";")?;
+                if let Some(step) = &s.step {
+                    write!(self.w, " ")?;
+                    self.write_expr(step)?;
+                }
+                write!(self.w, ") ")?;
+                self.write_block(&s.body)?;
+            }
+            Stmt::If(s) => self.write_if(s)?,
+            Stmt::Expr(e) => {
+                self.write_expr(e)?;
+                write!(self.w, ";")?;
+            }
+            Stmt::Decl(decl) => self.write_decl(decl)?,
+            Stmt::Return(s) => {
+                write!(self.w, "return")?;
+                if let Some(val) = &s.val {
+                    write!(self.w, " ")?;
+                    self.write_expr(val)?;
+                }
+                write!(self.w, ";")?;
+            }
+            Stmt::Break(_) => write!(self.w, "break;")?,
+            Stmt::Continue(_) => write!(self.w, "continue;")?,
+            Stmt::Empty(_) => write!(self.w, ";")?,
+            // Failed statements have no source representation
+            Stmt::Error(_) => write!(self.w, "// error")?,
+        }
+        self.write_trailing_comment(stmt.span())?;
+        writeln!(self.w)
+    }
+
+    fn write_if(&mut self, stmt: &IfStmt) -> Result {
+        write!(self.w, "if ")?;
+        self.write_expr(&stmt.cond)?;
+        write!(self.w, " ")?;
+        self.write_block(&stmt.if_block)?;
+        match &stmt.else_block {
+            IfElseBlock::None => Ok(()),
+            IfElseBlock::If(s) => {
+                write!(self.w, " else ")?;
+                self.write_if(s)
+            }
+            IfElseBlock::Block(b) => {
+                write!(self.w, " else ")?;
+                self.write_block(b)
+            }
+        }
+    }
+
+    fn write_decl(&mut self, decl: &DeclStmt) -> Result {
+        let kw = if decl.is_const { "const" } else { "let" };
+        write!(self.w, "{} {}: {}", kw, decl.name.name, decl.ty)?;
+        if let Some(val) = &decl.val {
+            write!(self.w, " = ")?;
+            self.write_expr(val)?;
+        }
+        write!(self.w, ";")
+    }
+
+    /// Writes `expr`, wrapping it in parentheses if it binds looser than
+    /// `min_prec`.
+    fn write_operand(&mut self, expr: &Expr, min_prec: u32) -> Result {
+        if precedence(expr) < min_prec {
+            write!(self.w, "(")?;
+            self.write_expr(expr)?;
+            write!(self.w, ")")
+        } else {
+            self.write_expr(expr)
+        }
+    }
+
+    fn write_expr(&mut self, expr: &Expr) -> Result {
+        match expr {
+            Expr::Ident(i) => write!(self.w, "{}", i.name),
+            Expr::Literal(lit) => write!(self.w, "{}", lit.kind),
+            Expr::Assign(e) => {
+                // Assignments are right associative
+                self.write_operand(&e.lhs, PREC_ASSIGN + 1)?;
+                match e.op {
+                    Some(op) => write!(self.w, " {}= ", op)?,
+                    None => write!(self.w, " = ")?,
+                }
+                self.write_operand(&e.rhs, PREC_ASSIGN)
+            }
+            Expr::Binary(e) => {
+                // Other binary operators are left associative
+                let prec = binary_precedence(e.op);
+                self.write_operand(&e.lhs, prec)?;
+                write!(self.w, " {} ", e.op)?;
+                self.write_operand(&e.rhs, prec + 1)
+            }
+            Expr::Unary(e) => match e.op {
+                // There's no prefix `+` in source code
+                UnaryOp::Pos => self.write_operand(&e.expr, PREC_UNARY),
+                op => {
+                    write!(self.w, "{}", op)?;
+                    // Nested unary operators are parenthesized so that they
+                    // don't get lexed as a single token
+                    self.write_operand(&e.expr, PREC_ITEM)
+                }
+            },
+            Expr::IncDec(e) if e.is_prefix => {
+                write!(self.w, "{}", e.op)?;
+                self.write_operand(&e.expr, PREC_ITEM)
+            }
+            Expr::IncDec(e) => {
+                self.write_operand(&e.expr, PREC_POSTFIX)?;
+                write!(self.w, "{}", e.op)
+            }
+            Expr::As(e) => {
+                self.write_operand(&e.val, PREC_AS)?;
+                write!(self.w, " as {}", e.ty)
+            }
+            Expr::Call(e) => {
+                write!(self.w, "{}(", e.func.name)?;
+                for (idx, param) in e.params.iter().enumerate() {
+                    if idx != 0 {
+                        write!(self.w, ", ")?;
+                    }
+                    self.write_expr(param)?;
+                }
+                write!(self.w, ")")
+            }
+            Expr::Index(e) => {
+                self.write_operand(&e.base, PREC_ITEM)?;
+                write!(self.w, "[")?;
+                self.write_expr(&e.index)?;
+                write!(self.w, "]")
+            }
+        }
+    }
+}
+
+impl Display for Program {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        PrettyPrinter::default().writer(f).write_program(self)
+    }
+}
+
+impl Display for FuncStmt {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        PrettyPrinter::default().writer(f).write_func(self)
+    }
+}
+
+impl Display for ExternFuncStmt {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        PrettyPrinter::default().writer(f).write_extern_func(self)
+    }
+}
+
+impl Display for Stmt {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        PrettyPrinter::default().writer(f).write_stmt(self)
+    }
+}
+
+impl Display for Expr {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        PrettyPrinter::default().writer(f).write_expr(self)
+    }
+}
+
+impl Display for ImportStmt {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        write!(f, "import \"")?;
+        for ch in self.path.chars() {
+            write_escaped(f, ch, '"')?;
+        }
+        write!(f, "\";")
+    }
+}
+
+impl Display for TyDef {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        match &self.kind {
+            TyDefKind::Named(name) => write!(f, "{}", name),
+            TyDefKind::Array { elem, len } => write!(f, "[{}; {}]", elem, len),
+        }
+    }
+}
+
+impl Display for LiteralKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        match self {
+            LiteralKind::Integer(i) => write!(f, "{}", i),
+            LiteralKind::Bool(b) => write!(f, "{}", b),
+            LiteralKind::Float(x) => {
+                // Float literals always need a fractional part
+                let s = format!("{:?}", x);
+                match s.find(['.', 'e']) {
+                    Some(idx) if s.as_bytes()[idx] == b'e' => {
+                        write!(f, "{}.0{}", &s[..idx], &s[idx..])
+                    }
+                    Some(_) => write!(f, "{}", s),
+                    None => write!(f, "{}.0", s),
+                }
+            }
+            LiteralKind::String(s) => {
+                write!(f, "\"")?;
+                for ch in s.chars() {
+                    write_escaped(f, ch, '"')?;
+                }
+                write!(f, "\"")
+            }
+            LiteralKind::Char(ch) => {
+                write!(f, "'")?;
+                write_escaped(f, *ch, '\'')?;
+                write!(f, "'")
+            }
+        }
+    }
+}
+
+/// Writes `ch` inside a string or character literal delimited by `quote`,
+/// using only the escapes the lexer accepts.
+fn write_escaped(f: &mut Formatter<'_>, ch: char, quote: char) -> Result {
+    match ch {
+        '\n' => write!(f, "\\n"),
+        '\r' => write!(f, "\\r"),
+        '\t' => write!(f, "\\t"),
+        '\\' => write!(f, "\\\\"),
+        ch if ch == quote => write!(f, "\\{}", ch),
+        ch => write!(f, "{}", ch),
+    }
+}
+
+impl Display for BinaryOp {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        let s = match self {
+            BinaryOp::Add => "+",
+            BinaryOp::Sub => "-",
+            BinaryOp::Mul => "*",
+            BinaryOp::Div => "/",
+            BinaryOp::Mod => "%",
+            BinaryOp::BitAnd => "&",
+            BinaryOp::BitOr => "|",
+            BinaryOp::Xor => "^",
+            BinaryOp::Shl => "<<",
+            BinaryOp::Shr => ">>",
+            BinaryOp::Gt => ">",
+            BinaryOp::Lt => "<",
+            BinaryOp::Ge => ">=",
+            BinaryOp::Le => "<=",
+            BinaryOp::Eq => "==",
+            BinaryOp::Neq => "!=",
+            BinaryOp::And => "&&",
+            BinaryOp::Or => "||",
+        };
+        f.write_str(s)
+    }
+}
+
+impl Display for UnaryOp {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        let s = match self {
+            UnaryOp::Neg => "-",
+            UnaryOp::Pos => "+",
+            UnaryOp::Not => "!",
+            UnaryOp::BitNot => "~",
+        };
+        f.write_str(s)
+    }
+}
+
+impl Display for IncDecOp {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        let s = match self {
+            IncDecOp::Inc => "++",
+            IncDecOp::Dec => "--",
+        };
+        f.write_str(s)
+    }
+}
+
+/// Displays the AST as an indented tree of node kinds and their spans, for
+/// debugging purposes.
+pub struct TreeDump<'a>(pub &'a Program);
+
+impl<'a> Display for TreeDump<'a> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        let mut w = TreeWriter { level: 0, f };
+        w.node("Program", None)?;
+        w.level += 1;
+        for item in items_in_source_order(self.0) {
+            match item {
+                Item::Import(import) => {
+                    w.node(format_args!("Import {:?}", import.path), Some(import.span))?
+                }
+                Item::Decl(decl) => w.decl(decl)?,
+                Item::Extern(func) => w.nested("ExternFunc", Some(func.span), |w| {
+                    w.ident(&func.name)?;
+                    w.params(&func.params)?;
+                    w.ty(&func.ret_ty)
+                })?,
+                Item::Func(func) => w.func(func)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+struct TreeWriter<'a, 'f> {
+    level: usize,
+    f: &'a mut Formatter<'f>,
+}
+
+impl<'a, 'f> TreeWriter<'a, 'f> {
+    /// Writes a line for a node with the given description.
+    fn node(&mut self, desc: impl Display, span: Option<Span>) -> Result {
+        for _ in 0..self.level {
+            write!(self.f, "  ")?;
+        }
+        match span {
+            Some(span) => writeln!(self.f, "{} {:?}", desc, span),
+            None => writeln!(self.f, "{}", desc),
+        }
+    }
+
+    /// Writes a node, and its children one level deeper.
+    fn nested(
+        &mut self,
+        desc: impl Display,
+        span: Option<Span>,
+        children: impl FnOnce(&mut Self) -> Result,
+    ) -> Result {
+        self.node(desc, span)?;
+        self.level += 1;
+        let res = children(self);
+        self.level -= 1;
+        res
+    }
+
+    fn ident(&mut self, ident: &Ident) -> Result {
+        self.node(format_args!("Ident {}", ident.name), Some(ident.span))
+    }
+
+    fn ty(&mut self, ty: &TyDef) -> Result {
+        self.node(format_args!("Ty {}", ty), Some(ty.span))
+    }
+
+    fn func(&mut self, func: &FuncStmt) -> Result {
+        self.nested("Func", Some(func.span), |w| {
+            w.ident(&func.name)?;
+            w.params(&func.params)?;
+            w.ty(&func.ret_ty)?;
+            w.block(&func.body)
+        })
+    }
+
+    fn params(&mut self, params: &[FuncParam]) -> Result {
+        for param in params {
+            let desc = if param.is_const {
+                "Param const"
+            } else {
+                "Param"
+            };
+            self.nested(desc, None, |w| {
+                w.ident(&param.name)?;
+                w.ty(&param.ty)
+            })?;
+        }
+        Ok(())
+    }
+
+    fn decl(&mut self, decl: &DeclStmt) -> Result {
+        let desc = if decl.is_const { "Decl const" } else { "Decl" };
+        self.nested(desc, Some(decl.span), |w| {
+            w.ident(&decl.name)?;
+            w.ty(&decl.ty)?;
+            match &decl.val {
+                Some(val) => w.expr(val),
+                None => Ok(()),
+            }
+        })
+    }
+
+    fn block(&mut self, block: &BlockStmt) -> Result {
+        self.nested("Block", Some(block.span), |w| {
+            block.stmts.iter().try_for_each(|stmt| w.stmt(stmt))
+        })
+    }
+
+    fn if_stmt(&mut self, stmt: &IfStmt) -> Result {
+        self.nested("If", Some(stmt.span), |w| {
+            w.expr(&stmt.cond)?;
+            w.block(&stmt.if_block)?;
+            match &stmt.else_block {
+                IfElseBlock::None => Ok(()),
+                IfElseBlock::If(s) => w.if_stmt(s),
+                IfElseBlock::Block(b) => w.block(b),
+            }
+        })
+    }
+
+    fn stmt(&mut self, stmt: &Stmt) -> Result {
+        match stmt {
+            Stmt::Block(b) => self.block(b),
+            Stmt::While(s) => self.nested("While", Some(s.span), |w| {
+                w.expr(&s.cond)?;
+                w.block(&s.body)
+            }),
+            Stmt::For(s) => self.nested("For", Some(s.span), |w| {
+                if let Some(init) = &s.init {
+                    w.stmt(init)?;
+                }
+                if let Some(cond) = &s.cond {
+                    w.expr(cond)?;
+                }
+                if let Some(step) = &s.step {
+                    w.expr(step)?;
+                }
+                w.block(&s.body)
+            }),
+            Stmt::If(s) => self.if_stmt(s),
+            Stmt::Expr(e) => self.nested("ExprStmt", Some(e.span()), |w| w.expr(e)),
+            Stmt::Decl(d) => self.decl(d),
+            Stmt::Return(s) => self.nested("Return", Some(s.span), |w| match &s.val {
+                Some(val) => w.expr(val),
+                None => Ok(()),
+            }),
+            Stmt::Break(span) => self.node("Break", Some(*span)),
+            Stmt::Continue(span) => self.node("Continue", Some(*span)),
+            Stmt::Empty(span) => self.node("Empty", Some(*span)),
+            Stmt::Error(span) => self.node("Error", Some(*span)),
+        }
+    }
+
+    fn expr(&mut self, expr: &Expr) -> Result {
+        match expr {
+            Expr::Ident(i) => self.ident(i),
+            Expr::Literal(lit) => self.node(format_args!("Literal {}", lit.kind), Some(lit.span)),
+            Expr::Assign(e) => {
+                let desc = match e.op {
+                    Some(op) => format!("Assign {:?}", op),
+                    None => "Assign".into(),
+                };
+                self.nested(desc, Some(e.span), |w| {
+                    w.expr(&e.lhs)?;
+                    w.expr(&e.rhs)
+                })
+            }
+            Expr::Binary(e) => self.nested(format_args!("Binary {:?}", e.op), Some(e.span), |w| {
+                w.expr(&e.lhs)?;
+                w.expr(&e.rhs)
+            }),
+            Expr::Unary(e) => self.nested(format_args!("Unary {:?}", e.op), Some(e.span), |w| {
+                w.expr(&e.expr)
+            }),
+            Expr::IncDec(e) => {
+                let fix = if e.is_prefix { "prefix" } else { "postfix" };
+                self.nested(
+                    format_args!("IncDec {:?} {}", e.op, fix),
+                    Some(e.span),
+                    |w| w.expr(&e.expr),
+                )
+            }
+            Expr::As(e) => self.nested("As", Some(e.span), |w| {
+                w.expr(&e.val)?;
+                w.ty(&e.ty)
+            }),
+            Expr::Call(e) => self.nested("Call", Some(e.span), |w| {
+                w.ident(&e.func)?;
+                e.params.iter().try_for_each(|param| w.expr(param))
+            }),
+            Expr::Index(e) => self.nested("Index", Some(e.span), |w| {
+                w.expr(&e.base)?;
+                w.expr(&e.index)
+            }),
+        }
+    }
+}
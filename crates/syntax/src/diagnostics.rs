@@ -0,0 +1,229 @@
+//! Human-readable rendering of errors that point into source code.
+//!
+//! A [`Diagnostic`] is a message attached to an optional [`Span`]. Rendering it
+//! against a source file with [`DiagnosticRenderer`] produces output like:
+//!
+//! ```text
+//! error: expected Semicolon
+//!  --> fib.c0:3:5
+//!   |
+//! 3 |     return r;
+//!   |     ^^^^^^
+//! ```
+
+use std::fmt::{self, Display, Write};
+
+use crate::span::Span;
+
+/// An index of line starts inside a source file, used to map byte offsets into
+/// line and column numbers.
+#[derive(Debug, Clone)]
+pub struct LineIndex {
+    /// Byte offset of the start of every line. Always contains at least `0`.
+    line_starts: Vec<usize>,
+    /// Total length of the source in bytes.
+    len: usize,
+}
+
+/// A position inside a source file. Both fields are zero-based; add one when
+/// displaying them to humans.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct LineCol {
+    pub line: usize,
+    /// Column counted in characters, not in bytes.
+    pub col: usize,
+}
+
+impl LineIndex {
+    pub fn new(src: &str) -> LineIndex {
+        let line_starts = std::iter::once(0)
+            .chain(src.match_indices('\n').map(|(idx, _)| idx + 1))
+            .collect();
+        LineIndex {
+            line_starts,
+            len: src.len(),
+        }
+    }
+
+    /// Number of lines in this file.
+    pub fn line_count(&self) -> usize {
+        self.line_starts.len()
+    }
+
+    /// Returns the zero-based line number that contains byte offset `idx`.
+    /// Offsets past the end of file (e.g. [`Span::eof()`]) map to the last line.
+    pub fn line_of(&self, idx: usize) -> usize {
+        let idx = idx.min(self.len);
+        match self.line_starts.binary_search(&idx) {
+            Ok(line) => line,
+            Err(next_line) => next_line - 1,
+        }
+    }
+
+    /// Returns the byte range of the given line, excluding the line break.
+    pub fn line_range(&self, src: &str, line: usize) -> std::ops::Range<usize> {
+        let start = self.line_starts[line];
+        let end = self
+            .line_starts
+            .get(line + 1)
+            .map_or(self.len, |&next| next - 1);
+        let end = if src[start..end].ends_with('\r') {
+            end - 1
+        } else {
+            end
+        };
+        start..end
+    }
+
+    /// Maps byte offset `idx` into a line-column pair.
+    pub fn line_col(&self, src: &str, idx: usize) -> LineCol {
+        let idx = idx.min(self.len);
+        let line = self.line_of(idx);
+        let start = self.line_starts[line];
+        LineCol {
+            line,
+            col: src[start..idx].chars().count(),
+        }
+    }
+
+    /// Maps a line-column pair back into a byte offset. Columns past the end of
+    /// the line are clamped to the end of the line.
+    pub fn offset_of(&self, src: &str, pos: LineCol) -> usize {
+        if pos.line >= self.line_count() {
+            return self.len;
+        }
+        let range = self.line_range(src, pos.line);
+        src[range.clone()]
+            .char_indices()
+            .nth(pos.col)
+            .map_or(range.end, |(idx, _)| range.start + idx)
+    }
+}
+
+/// How severe a diagnostic is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Level {
+    Error,
+    Warning,
+    Note,
+}
+
+impl Display for Level {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Level::Error => write!(f, "error"),
+            Level::Warning => write!(f, "warning"),
+            Level::Note => write!(f, "note"),
+        }
+    }
+}
+
+/// A message pointing at some place in the source code.
+#[derive(Debug, Clone)]
+pub struct Diagnostic {
+    pub level: Level,
+    pub message: String,
+    pub span: Option<Span>,
+    /// Text shown next to the caret underline.
+    pub label: Option<String>,
+}
+
+impl Diagnostic {
+    pub fn new(level: Level, message: impl Into<String>, span: Option<Span>) -> Diagnostic {
+        Diagnostic {
+            level,
+            message: message.into(),
+            span,
+            label: None,
+        }
+    }
+
+    pub fn error(message: impl Into<String>, span: Option<Span>) -> Diagnostic {
+        Self::new(Level::Error, message, span)
+    }
+
+    pub fn with_label(mut self, label: impl Into<String>) -> Diagnostic {
+        self.label = Some(label.into());
+        self
+    }
+}
+
+/// Renders diagnostics against a single source file.
+pub struct DiagnosticRenderer<'a> {
+    file_name: &'a str,
+    src: &'a str,
+    lines: LineIndex,
+}
+
+impl<'a> DiagnosticRenderer<'a> {
+    pub fn new(file_name: &'a str, src: &'a str) -> DiagnosticRenderer<'a> {
+        DiagnosticRenderer {
+            file_name,
+            src,
+            lines: LineIndex::new(src),
+        }
+    }
+
+    pub fn line_index(&self) -> &LineIndex {
+        &self.lines
+    }
+
+    /// Renders the diagnostic into a string. The result ends with a newline.
+    pub fn render(&self, diag: &Diagnostic) -> String {
+        let mut s = String::new();
+        self.render_to(&mut s, diag)
+            .expect("Writing into a string never fails");
+        s
+    }
+
+    pub fn render_to<W: Write>(&self, w: &mut W, diag: &Diagnostic) -> fmt::Result {
+        writeln!(w, "{}: {}", diag.level, diag.message)?;
+
+        let span = match diag.span {
+            Some(span) => span,
+            None => {
+                return writeln!(w, " --> {}", self.file_name);
+            }
+        };
+
+        // `Span::eof()` and other out-of-bound spans point at the end of file.
+        let start = span.start().min(self.src.len());
+        let end = span.end().max(start).min(self.src.len());
+
+        let pos = self.lines.line_col(self.src, start);
+        let line_no = (pos.line + 1).to_string();
+        let gutter = " ".repeat(line_no.len());
+
+        writeln!(
+            w,
+            "{}--> {}:{}:{}",
+            gutter,
+            self.file_name,
+            pos.line + 1,
+            pos.col + 1
+        )?;
+        writeln!(w, "{} |", gutter)?;
+
+        let range = self.lines.line_range(self.src, pos.line);
+        let line = &self.src[range.clone()];
+        writeln!(w, "{} | {}", line_no, line)?;
+
+        // Spans covering multiple lines are only underlined until the end of
+        // their first line.
+        let underline_end = end.min(range.end);
+        let leading = &self.src[range.start..start];
+        let underlined = &self.src[start..underline_end];
+
+        write!(w, "{} | ", gutter)?;
+        for ch in leading.chars() {
+            // Keep tabs so the carets line up with the line above
+            w.write_char(if ch == '\t' { '\t' } else { ' ' })?;
+        }
+        let caret_count = underlined.chars().count().max(1);
+        write!(w, "{}", "^".repeat(caret_count))?;
+        if let Some(label) = &diag.label {
+            write!(w, " {}", label)?;
+        }
+        writeln!(w)
+    }
+}
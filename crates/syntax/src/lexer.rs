@@ -1,6 +1,11 @@
 use logos::Logos;
 
-use crate::{prelude::Span, token::Token};
+use std::fmt::Display;
+
+use crate::{
+    prelude::Span,
+    token::{block_comment_len, Token},
+};
 
 pub type Lexer<'src> = logos::Lexer<'src, Token>;
 
@@ -8,8 +13,142 @@ pub fn lexer(s: &str) -> Lexer {
     Token::lexer(s)
 }
 
-pub fn spanned_lexer(s: &str) -> impl Iterator<Item = (Token, Span)> + '_ {
-    Token::lexer(s)
-        .spanned()
-        .map(|(t, s)| (t, crate::prelude::Span::new_idx(s.start, s.end)))
+/// A token with its position in the source code.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde_impl", derive(serde::Serialize, serde::Deserialize))]
+pub struct SpannedToken {
+    pub token: Token,
+    pub span: Span,
+}
+
+/// Kinds of malformed source code found by the lexer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde_impl", derive(serde::Serialize, serde::Deserialize))]
+pub enum LexErrorKind {
+    /// A character that doesn't start any token
+    InvalidChar,
+    /// An integer literal larger than the largest 64-bit signed integer
+    IntOverflow,
+    /// An integer literal with a radix prefix but no digits, e.g. `0x_`
+    MissingDigits,
+    /// A string literal without its closing quote
+    UnterminatedString,
+    /// An unknown escape sequence inside a string or character literal
+    BadEscape,
+    /// A character literal that doesn't contain exactly one character
+    InvalidCharLiteral,
+    /// A block comment without its closing `*/`
+    UnterminatedComment,
+}
+
+impl Display for LexErrorKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LexErrorKind::InvalidChar => write!(f, "invalid character"),
+            LexErrorKind::IntOverflow => write!(f, "integer literal is too large"),
+            LexErrorKind::MissingDigits => write!(f, "integer literal has no digits"),
+            LexErrorKind::UnterminatedString => write!(f, "unterminated string literal"),
+            LexErrorKind::BadEscape => write!(f, "unknown escape sequence"),
+            LexErrorKind::InvalidCharLiteral => {
+                write!(f, "character literal must contain exactly one character")
+            }
+            LexErrorKind::UnterminatedComment => write!(f, "unterminated block comment"),
+        }
+    }
+}
+
+/// A piece of malformed source code found by the lexer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LexError {
+    pub kind: LexErrorKind,
+    pub span: Span,
+}
+
+impl Display for LexError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} at {:?}", self.kind, self.span)
+    }
+}
+
+/// Lexes `s` into tokens and their spans. Unlike [`lexer`], malformed source
+/// code is returned as a [`LexError`] telling what's wrong with it instead of
+/// an opaque [`Token::Error`].
+pub fn spanned_lexer(s: &str) -> impl Iterator<Item = Result<(Token, Span), LexError>> + '_ {
+    let mut lexer = Token::lexer(s);
+    std::iter::from_fn(move || {
+        let token = lexer.next()?;
+        let span = lexer.span();
+        let span = Span::new_idx(span.start, span.end);
+        let kind = match token {
+            Token::Error => lexer.extras.take().unwrap_or(LexErrorKind::InvalidChar),
+            Token::LexError(kind) => kind,
+            token => return Some(Ok((token, span))),
+        };
+        Some(Err(LexError { kind, span }))
+    })
+}
+
+/// A piece of source code inside a lossless token stream.
+#[derive(Debug, Clone)]
+pub enum LosslessToken {
+    Token(Token),
+    /// Malformed source code
+    Error(LexErrorKind),
+    /// Whitespace, including newlines
+    Whitespace,
+    /// A block comment, or a `//` comment not including the newline that
+    /// ends it
+    Comment,
+}
+
+/// A lexer that keeps the whitespace and comments skipped by [`lexer`].
+///
+/// The spans of all returned tokens cover the whole input without gaps, so
+/// concatenating them gives back the original source code.
+pub fn lossless_lexer(s: &str) -> impl Iterator<Item = (LosslessToken, Span)> + '_ {
+    let mut pos = 0;
+    spanned_lexer(s)
+        .map(|tok| {
+            Some(match tok {
+                Ok((tok, span)) => (LosslessToken::Token(tok), span),
+                Err(e) => (LosslessToken::Error(e.kind), e.span),
+            })
+        })
+        .chain(std::iter::once(None))
+        .flat_map(move |tok| {
+            let gap_end = tok.as_ref().map_or(s.len(), |(_, span)| span.start());
+            let trivia = split_trivia(s, pos, gap_end);
+            if let Some((_, span)) = &tok {
+                pos = span.end();
+            }
+            trivia.into_iter().chain(tok)
+        })
+}
+
+/// Splits the skipped source code between `start` and `end` into whitespace
+/// and comments.
+fn split_trivia(s: &str, start: usize, end: usize) -> Vec<(LosslessToken, Span)> {
+    let mut res = vec![];
+    let mut pos = start;
+    while pos < end {
+        let rest = &s[pos..end];
+        let (kind, len) = if rest.starts_with("//") {
+            (
+                LosslessToken::Comment,
+                rest.find('\n').unwrap_or(rest.len()),
+            )
+        } else if rest.starts_with("/*") {
+            // Unterminated block comments are lexing errors, so this one is closed
+            (LosslessToken::Comment, block_comment_len(rest).unwrap())
+        } else {
+            // Only comments start with a slash here
+            (
+                LosslessToken::Whitespace,
+                rest.find('/').unwrap_or(rest.len()),
+            )
+        };
+        res.push((kind, Span::new(pos, len)));
+        pos += len;
+    }
+    res
 }
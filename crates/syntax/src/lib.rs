@@ -4,6 +4,9 @@ pub mod span;
 /// Utilities
 pub mod util;
 
+/// Human-readable error reporting
+pub mod diagnostics;
+
 /// Lexer for r0 tokens
 pub mod lexer;
 /// Models of r0 tokens
@@ -17,15 +20,120 @@ pub mod parser;
 /// Visitor trait for working with AST
 pub mod visitor;
 
+/// Name resolution over the AST
+pub mod scope;
+
+/// Programs made of multiple source files
+pub mod source_map;
+
+mod test;
+
 pub use lexer::Lexer;
 pub use token::Token;
 
+use span::FileId;
+
 pub mod prelude {
     pub use crate::span::Span;
     pub use crate::util::{Mut, MutWeak, P};
 }
 
 pub fn parse(program: &str) -> Result<ast::Program, parser::err::ParseError> {
-    let mut parser = parser::Parser::new(lexer::spanned_lexer(program));
-    parser.parse()
+    let (tokens, lex_errors, _) = lex_for_parser(program, FileId::default());
+    let mut parser = parser::Parser::new(tokens.into_iter());
+    let res = parser.parse();
+    // Report whichever error comes first in the source code
+    match (lex_errors.into_iter().next(), res) {
+        (Some(lex_err), Err(e)) if error_pos(&e) < error_pos(&lex_err) => Err(e),
+        (Some(lex_err), _) => Err(lex_err),
+        (None, res) => res,
+    }
+}
+
+/// Parse the program, reporting as many syntax errors as possible instead of
+/// stopping at the first one. See [`Parser::parse_recovering`](parser::Parser::parse_recovering).
+pub fn parse_recovering(program: &str) -> (ast::Program, Vec<parser::err::ParseError>) {
+    parse_file(program, FileId::default())
+}
+
+/// Like [`parse_recovering`], but all spans in the result point into `file`.
+/// Used for programs made of multiple files, see [`source_map::SourceMap`].
+pub fn parse_file(program: &str, file: FileId) -> (ast::Program, Vec<parser::err::ParseError>) {
+    let (tokens, mut errors, after_dropped) = lex_for_parser(program, file);
+    let mut parser = parser::Parser::new(tokens.into_iter());
+    let (program, parse_errors) = parser.parse_recovering();
+    // A syntax error right after a dropped token is caused by the token
+    // missing, which is already reported
+    let parse_errors = parse_errors.into_iter().filter(|e| {
+        !e.span
+            .is_some_and(|span| after_dropped.contains(&span.start()))
+    });
+    // Errors at the end of file don't come from any token
+    errors.extend(parse_errors.map(|mut e| {
+        e.span = e.span.map(|span| span.with_file(file));
+        e
+    }));
+    errors.sort_by_key(error_pos);
+    (program, errors)
+}
+
+/// Lexes the whole program for the parser, turning lexing errors into parse
+/// errors. Malformed literals are replaced by placeholders, so that the
+/// parser doesn't report them again as syntax errors. Other invalid tokens are
+/// dropped, and the positions of the tokens right after them are returned.
+fn lex_for_parser(
+    program: &str,
+    file: FileId,
+) -> (
+    Vec<(Token, span::Span)>,
+    Vec<parser::err::ParseError>,
+    Vec<usize>,
+) {
+    let mut tokens = vec![];
+    let mut errors = vec![];
+    let mut after_dropped = vec![];
+    let mut dropped = false;
+    for tok in lexer::spanned_lexer(program) {
+        match tok {
+            Ok((tok, span)) => {
+                if dropped {
+                    after_dropped.push(span.start());
+                    dropped = false;
+                }
+                tokens.push((tok, span.with_file(file)))
+            }
+            Err(mut e) => {
+                e.span = e.span.with_file(file);
+                let placeholder =
+                    match e.kind {
+                        lexer::LexErrorKind::IntOverflow | lexer::LexErrorKind::MissingDigits => {
+                            Some(Token::UIntLiteral(0))
+                        }
+                        lexer::LexErrorKind::UnterminatedString => {
+                            Some(Token::StringLiteral(String::new()))
+                        }
+                        lexer::LexErrorKind::BadEscape
+                            if program[e.span.start()..].starts_with('"') =>
+                        {
+                            Some(Token::StringLiteral(String::new()))
+                        }
+                        lexer::LexErrorKind::BadEscape
+                        | lexer::LexErrorKind::InvalidCharLiteral => Some(Token::CharLiteral('\0')),
+                        lexer::LexErrorKind::InvalidChar
+                        | lexer::LexErrorKind::UnterminatedComment => None,
+                    };
+                dropped |= placeholder.is_none();
+                tokens.extend(placeholder.map(|tok| (tok, e.span)));
+                errors.push(parser::err::ParseError::new_span(
+                    parser::err::ParseErrorKind::Lex(e.kind),
+                    e.span,
+                ));
+            }
+        }
+    }
+    (tokens, errors, after_dropped)
+}
+
+fn error_pos(e: &parser::err::ParseError) -> usize {
+    e.span.map_or(usize::MAX, |span| span.start())
 }
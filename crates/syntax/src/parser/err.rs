@@ -1,4 +1,6 @@
-use crate::{prelude::Span, Token};
+use std::fmt::Display;
+
+use crate::{diagnostics::Diagnostic, lexer::LexErrorKind, prelude::Span, Token};
 
 #[derive(Debug)]
 pub struct ParseError {
@@ -21,12 +23,57 @@ impl ParseError {
     pub fn new(kind: ParseErrorKind, span: Option<Span>) -> Self {
         Self { kind, span }
     }
+
+    /// Converts this error into a diagnostic for rendering.
+    pub fn to_diagnostic(&self) -> Diagnostic {
+        let span = match (&self.kind, self.span) {
+            (ParseErrorKind::UnexpectedEof, None) => Some(Span::eof()),
+            (_, span) => span,
+        };
+        Diagnostic::error(self.kind.to_string(), span)
+    }
+}
+
+impl Display for ParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.span {
+            Some(span) if span.is_eof() => write!(f, "{} at end of file", self.kind),
+            Some(span) => write!(f, "{} at {:?}", self.kind, span),
+            None => write!(f, "{}", self.kind),
+        }
+    }
 }
 
 #[derive(Debug)]
 pub enum ParseErrorKind {
+    /// Malformed source code found by the lexer
+    Lex(LexErrorKind),
     ExpectToken(Token),
     ExpectedPattern(String),
     UnexpectedEof,
+    /// An imported file that couldn't be read
+    ImportFailed {
+        path: String,
+        reason: String,
+    },
     Dummy,
 }
+
+impl Display for ParseErrorKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ParseErrorKind::Lex(kind) => write!(f, "{}", kind),
+            ParseErrorKind::ExpectToken(t) => write!(f, "expected {}", t),
+            ParseErrorKind::ExpectedPattern(p) => {
+                // Patterns come from `stringify!`, e.g. `Token::Ident(_) | Token::Semicolon`
+                let p = p.replace("Token::", "").replace("(_)", "");
+                write!(f, "expected {}", p)
+            }
+            ParseErrorKind::UnexpectedEof => write!(f, "unexpected end of file"),
+            ParseErrorKind::ImportFailed { path, reason } => {
+                write!(f, "cannot import `{}`: {}", path, reason)
+            }
+            ParseErrorKind::Dummy => write!(f, "unknown error"),
+        }
+    }
+}
@@ -13,6 +13,12 @@ use crate::ast::*;
 
 pub struct Parser<L> {
     pub lexer: L,
+
+    /// Whether to recover from syntax errors instead of bailing out.
+    recover: bool,
+
+    /// Errors encountered and recovered from.
+    errors: Vec<ParseError>,
 }
 
 macro_rules! expect {
@@ -56,12 +62,18 @@ macro_rules! separated {
 /// Requires `op` to be a binary operator, aka `op.is_binary_op() == true`
 fn combine_expr(lhs: Expr, rhs: Expr, op: Token) -> Expr {
     match op {
-        Token::Assign => {
+        Token::Assign
+        | Token::AddAssign
+        | Token::SubAssign
+        | Token::MulAssign
+        | Token::DivAssign
+        | Token::ModAssign => {
             let span = lhs.span() + rhs.span();
             Expr::Assign(AssignExpr {
                 lhs: P::new(lhs),
                 rhs: P::new(rhs),
                 allow_assign_const: false,
+                op: op.to_compound_assign_op(),
                 span,
             })
         }
@@ -80,17 +92,6 @@ fn combine_expr(lhs: Expr, rhs: Expr, op: Token) -> Expr {
     }
 }
 
-macro_rules! repeated {
-    ( $parse:expr, $detect_sep:expr) => {{
-        let mut v = vec![];
-        while !$detect_sep {
-            let val: Result<_, ParseError> = (|| $parse)();
-            v.push(val?);
-        }
-        v
-    }};
-}
-
 impl<L> Parser<Peekable<L>>
 where
     L: Iterator<Item = (Token, Span)>,
@@ -98,6 +99,8 @@ where
     pub fn new(lexer: L) -> Parser<Peekable<L>> {
         Parser {
             lexer: lexer.peekable(),
+            recover: false,
+            errors: vec![],
         }
     }
 
@@ -105,6 +108,30 @@ where
         self.parse_program()
     }
 
+    /// Parse the program, recovering from syntax errors as much as possible.
+    ///
+    /// Statements that fail to parse are replaced with [`Stmt::Error`] placeholders,
+    /// and the parser skips ahead to the next `;`, `}` or top-level item. Returns
+    /// the (possibly partial) program and all errors encountered.
+    pub fn parse_recovering(&mut self) -> (Program, Vec<ParseError>) {
+        self.recover = true;
+        let program = self.parse_program();
+        let mut errors = std::mem::take(&mut self.errors);
+        let program = match program {
+            Ok(p) => p,
+            Err(e) => {
+                errors.push(e);
+                Program {
+                    imports: vec![],
+                    decls: vec![],
+                    externs: vec![],
+                    funcs: vec![],
+                }
+            }
+        };
+        (program, errors)
+    }
+
     fn peek(&mut self) -> Option<&Token> {
         self.lexer.peek().map(|(t, _)| t)
     }
@@ -121,24 +148,132 @@ where
         }
     }
 
+    /// Skip tokens until the next statement boundary. A `;` is consumed, while
+    /// `}` and keywords starting a declaration are left for the caller. Braces
+    /// opened while skipping are skipped along with their contents, and the
+    /// statement ends with them.
+    ///
+    /// Returns the span of skipped tokens.
+    fn synchronize_stmt(&mut self, err: ParseError) -> Span {
+        let mut span = err.span.filter(|s| *s != Span::eof());
+        self.errors.push(err);
+
+        let mut depth = 0usize;
+        let mut break_after = false;
+        while let Some((token, token_span)) = self.lexer.peek() {
+            match token {
+                Token::RBrace if depth == 0 => break,
+                Token::FnKw | Token::ExternKw | Token::ImportKw | Token::LetKw | Token::ConstKw
+                    if depth == 0 =>
+                {
+                    break
+                }
+                Token::LBrace => depth += 1,
+                Token::Semicolon if depth == 0 => break_after = true,
+                // A block skipped as a whole also ends the statement
+                Token::RBrace if depth == 1 => break_after = true,
+                Token::RBrace => depth -= 1,
+                _ => {}
+            }
+            let token_span = *token_span;
+            self.lexer.next();
+            span = Some(span.map_or(token_span, |s| s + token_span));
+            if break_after {
+                break;
+            }
+        }
+        span.unwrap_or_else(Span::eof)
+    }
+
+    /// Skip tokens until the next top-level item, i.e. a `fn` keyword or an
+    /// `extern`, `import`, `let` or `const` keyword outside of any braces.
+    fn synchronize_item(&mut self, err: ParseError) {
+        self.errors.push(err);
+
+        let mut depth = 0usize;
+        while let Some((token, _)) = self.lexer.peek() {
+            match token {
+                Token::FnKw => break,
+                Token::ExternKw | Token::ImportKw | Token::LetKw | Token::ConstKw if depth == 0 => {
+                    break
+                }
+                Token::LBrace => depth += 1,
+                Token::RBrace => depth = depth.saturating_sub(1),
+                _ => {}
+            }
+            self.lexer.next();
+        }
+    }
+
     fn parse_program(&mut self) -> Result<Program, ParseError> {
+        let mut imports = vec![];
+        let mut externs = vec![];
         let mut funcs = vec![];
         let mut decls = vec![];
         loop {
-            if is_next!(self, Token::FnKw) {
-                let res = self.parse_fn_decl()?;
-                funcs.push(res);
+            if is_next!(self, Token::ImportKw) {
+                match self.parse_import() {
+                    Ok(res) => imports.push(res),
+                    Err(e) if self.recover => self.synchronize_item(e),
+                    Err(e) => return Err(e),
+                }
+            } else if is_next!(self, Token::ExternKw) {
+                match self.parse_extern_fn_decl() {
+                    Ok(res) => externs.push(res),
+                    Err(e) if self.recover => self.synchronize_item(e),
+                    Err(e) => return Err(e),
+                }
+            } else if is_next!(self, Token::FnKw) {
+                match self.parse_fn_decl() {
+                    Ok(res) => funcs.push(res),
+                    Err(e) if self.recover => self.synchronize_item(e),
+                    Err(e) => return Err(e),
+                }
             } else if is_next!(self, Token::LetKw) {
-                let res = self.parse_decl()?;
-                decls.push(res);
+                match self.parse_decl() {
+                    Ok(res) => decls.push(res),
+                    Err(e) if self.recover => self.synchronize_item(e),
+                    Err(e) => return Err(e),
+                }
             } else if is_next!(self, Token::ConstKw) {
-                let res = self.parse_const_decl()?;
-                decls.push(res);
+                match self.parse_const_decl() {
+                    Ok(res) => decls.push(res),
+                    Err(e) if self.recover => self.synchronize_item(e),
+                    Err(e) => return Err(e),
+                }
+            } else if let Some((_, span)) = self.lexer.peek() {
+                let err = ParseError::new_span(
+                    ParseErrorKind::ExpectedPattern("extern, fn, import, let or const".into()),
+                    *span,
+                );
+                if self.recover {
+                    // Always make progress, even if we're stuck on a stray `}`
+                    self.lexer.next();
+                    self.synchronize_item(err);
+                } else {
+                    return Err(err);
+                }
             } else {
                 break;
             }
         }
-        Ok(Program { decls, funcs })
+        Ok(Program {
+            imports,
+            decls,
+            externs,
+            funcs,
+        })
+    }
+
+    fn parse_import(&mut self) -> Result<ImportStmt, ParseError> {
+        // Import -> 'import' StringLiteral ';'
+        let (_, start_span) = expect!(self, Token::ImportKw)?;
+        let (path, _) = expect!(self, Token::StringLiteral(_))?;
+        let (_, end_span) = expect!(self, Token::Semicolon)?;
+        Ok(ImportStmt {
+            span: start_span + end_span,
+            path: path.get_string_owned().unwrap(),
+        })
     }
 
     fn parse_ident(&mut self) -> Result<Ident, ParseError> {
@@ -150,11 +285,26 @@ where
     }
 
     fn parse_ty(&mut self) -> Result<TyDef, ParseError> {
+        // TypeDef -> Ident | '[' TypeDef ';' UIntLiteral ']'
+        if is_next!(self, Token::LBracket) {
+            let (_, start_span) = expect!(self, Token::LBracket)?;
+            let elem = self.parse_ty()?;
+            expect!(self, Token::Semicolon)?;
+            let (len, _) = expect!(self, Token::UIntLiteral(_))?;
+            let (_, end_span) = expect!(self, Token::RBracket)?;
+            return Ok(TyDef {
+                span: start_span + end_span,
+                kind: TyDefKind::Array {
+                    elem: P::new(elem),
+                    len: len.get_uint().unwrap(),
+                },
+            });
+        }
+
         let (name, name_span) = expect!(self, Token::Ident(_))?;
         Ok(TyDef {
             span: name_span,
-            name: name.get_ident_owned().unwrap(),
-            params: None,
+            kind: TyDefKind::Named(name.get_ident_owned().unwrap()),
         })
     }
 
@@ -243,6 +393,12 @@ where
                 span,
                 kind: LiteralKind::Integer(num.get_uint().unwrap()),
             }))
+        } else if is_next!(self, Token::TrueKw | Token::FalseKw) {
+            let (val, span) = self.lexer.next().unwrap();
+            Ok(Expr::Literal(LiteralExpr {
+                span,
+                kind: LiteralKind::Bool(matches!(val, Token::TrueKw)),
+            }))
         } else if is_next!(self, Token::FloatLiteral(_)) {
             let (num, span) = self.lexer.next().unwrap();
             Ok(Expr::Literal(LiteralExpr {
@@ -276,19 +432,54 @@ where
     }
 
     fn parse_unary_expr(&mut self) -> Result<Expr, ParseError> {
-        // UExpr -> PreUOp* Item ProUOp*
-        // PreUOp -> '+' | '-'
+        // UExpr -> PreUOp* Item Index* IncDec* ProUOp*
+        // PreUOp -> '+' | '-' | '!' | '~' | '++' | '--'
+        // Index -> '[' Expr ']'
+        // IncDec -> '++' | '--'
         // ProUOp -> 'as' TypeDef
         let mut prec_ops = vec![];
-        while is_next!(self, Token::Minus) {
+        while is_next!(
+            self,
+            Token::Minus | Token::Not | Token::BitNot | Token::Inc | Token::Dec
+        ) {
             prec_ops.push(self.lexer.next().unwrap())
         }
 
         let mut item = self.parse_item()?;
+        while is_next!(self, Token::LBracket) {
+            self.lexer.next();
+            let index = self.parse_expr()?;
+            let (_, end_span) = expect!(self, Token::RBracket)?;
+            item = Expr::Index(IndexExpr {
+                span: item.span() + end_span,
+                base: P::new(item),
+                index: P::new(index),
+            });
+        }
+        while is_next!(self, Token::Inc | Token::Dec) {
+            let (op, span) = self.lexer.next().unwrap();
+            item = Expr::IncDec(IncDecExpr {
+                span: item.span() + span,
+                op: op.to_inc_dec_op().unwrap(),
+                is_prefix: false,
+                expr: P::new(item),
+            });
+        }
         for (prec_op, span) in prec_ops.drain(..).rev() {
+            if let Some(op) = prec_op.to_inc_dec_op() {
+                item = Expr::IncDec(IncDecExpr {
+                    span: item.span() + span,
+                    op,
+                    is_prefix: true,
+                    expr: P::new(item),
+                });
+                continue;
+            }
             let unary_op = match prec_op {
                 Token::Plus => UnaryOp::Pos,
                 Token::Minus => UnaryOp::Neg,
+                Token::Not => UnaryOp::Not,
+                Token::BitNot => UnaryOp::BitNot,
                 _ => unreachable!(),
             };
             item = Expr::Unary(UnaryExpr {
@@ -348,8 +539,32 @@ where
 
     fn parse_block(&mut self) -> Result<BlockStmt, ParseError> {
         let (_, _start_span) = expect!(self, Token::LBrace)?;
-        let vals = repeated!(self.parse_stmt(), is_next!(self, Token::RBrace));
-        let (_, _end_span) = expect!(self, Token::RBrace)?;
+        let mut vals = vec![];
+        while !is_next!(self, Token::RBrace) {
+            match self.parse_stmt() {
+                Ok(stmt) => vals.push(stmt),
+                Err(e) if self.recover => {
+                    let span = self.synchronize_stmt(e);
+                    vals.push(Stmt::Error(span));
+                    // We're stuck at the start of another function or at the
+                    // end of file, so this block is never closed.
+                    if self.lexer.peek().is_none()
+                        || is_next!(self, Token::FnKw | Token::ExternKw | Token::ImportKw)
+                    {
+                        break;
+                    }
+                }
+                Err(e) => return Err(e),
+            }
+        }
+        let _end_span = match expect!(self, Token::RBrace) {
+            Ok((_, span)) => span,
+            Err(e) if self.recover => {
+                self.errors.push(e);
+                vals.last().map_or(_start_span, |s| s.span())
+            }
+            Err(e) => return Err(e),
+        };
         Ok(BlockStmt {
             stmts: vals,
             span: _start_span + _end_span,
@@ -401,6 +616,44 @@ where
         })
     }
 
+    fn parse_for_stmt(&mut self) -> Result<ForStmt, ParseError> {
+        let (_, start_span) = expect!(self, Token::ForKw)?;
+        expect!(self, Token::LParen)?;
+
+        let init = if is_next!(self, Token::Semicolon) {
+            self.lexer.next();
+            None
+        } else if is_next!(self, Token::LetKw) {
+            Some(P::new(Stmt::Decl(self.parse_decl()?)))
+        } else {
+            Some(P::new(Stmt::Expr(self.parse_expr_stmt()?)))
+        };
+
+        let cond = if !is_next!(self, Token::Semicolon) {
+            Some(P::new(self.parse_expr()?))
+        } else {
+            None
+        };
+        expect!(self, Token::Semicolon)?;
+
+        let step = if !is_next!(self, Token::RParen) {
+            Some(P::new(self.parse_expr()?))
+        } else {
+            None
+        };
+        expect!(self, Token::RParen)?;
+
+        let body = self.parse_block()?;
+        let span = start_span + body.span;
+        Ok(ForStmt {
+            init,
+            cond,
+            step,
+            body: P::new(body),
+            span,
+        })
+    }
+
     fn parse_return_stmt(&mut self) -> Result<ReturnStmt, ParseError> {
         let (_, _start_span) = expect!(self, Token::ReturnKw)?;
 
@@ -440,6 +693,8 @@ where
             Stmt::If(self.parse_if_stmt()?)
         } else if is_next!(self, Token::WhileKw) {
             Stmt::While(self.parse_while_stmt()?)
+        } else if is_next!(self, Token::ForKw) {
+            Stmt::For(self.parse_for_stmt()?)
         } else if is_next!(self, Token::BreakKw) {
             Stmt::Break(self.parse_break_stmt()?)
         } else if is_next!(self, Token::ContinueKw) {
@@ -456,6 +711,38 @@ where
 
     fn parse_fn_decl(&mut self) -> Result<FuncStmt, ParseError> {
         let (_, _start_span) = expect!(self, Token::FnKw)?;
+        let (fn_name, params, ret_ty) = self.parse_fn_signature()?;
+
+        let body = self.parse_block()?;
+
+        let span = _start_span + body.span;
+
+        Ok(FuncStmt {
+            name: fn_name,
+            params,
+            ret_ty,
+            body,
+            span,
+        })
+    }
+
+    fn parse_extern_fn_decl(&mut self) -> Result<ExternFuncStmt, ParseError> {
+        // ExternFn -> 'extern' 'fn' Ident '(' Params ')' '->' Ty ';'
+        let (_, start_span) = expect!(self, Token::ExternKw)?;
+        expect!(self, Token::FnKw)?;
+        let (name, params, ret_ty) = self.parse_fn_signature()?;
+        let (_, end_span) = expect!(self, Token::Semicolon)?;
+        Ok(ExternFuncStmt {
+            span: start_span + end_span,
+            name,
+            params,
+            ret_ty,
+        })
+    }
+
+    /// Parses the part of a function declaration after `fn` and before the
+    /// body, i.e. its name, parameters and return type.
+    fn parse_fn_signature(&mut self) -> Result<(Ident, Vec<FuncParam>, TyDef), ParseError> {
         let fn_name = self.parse_ident()?;
 
         expect!(self, Token::LParen)?;
@@ -481,18 +768,7 @@ where
 
         expect!(self, Token::Arrow)?;
         let ret_ty = self.parse_ty()?;
-
-        let body = self.parse_block()?;
-
-        let span = _start_span + body.span;
-
-        Ok(FuncStmt {
-            name: fn_name,
-            params,
-            ret_ty,
-            body,
-            span,
-        })
+        Ok((fn_name, params, ret_ty))
     }
 }
 
@@ -504,13 +780,26 @@ impl Token {
                 | Token::Minus
                 | Token::Mul
                 | Token::Div
+                | Token::Mod
+                | Token::BitAnd
+                | Token::BitOr
+                | Token::Xor
+                | Token::Shl
+                | Token::Shr
                 | Token::Assign
+                | Token::AddAssign
+                | Token::SubAssign
+                | Token::MulAssign
+                | Token::DivAssign
+                | Token::ModAssign
                 | Token::Eq
                 | Token::Neq
                 | Token::Lt
                 | Token::Gt
                 | Token::Le
                 | Token::Ge
+                | Token::And
+                | Token::Or
         )
     }
 
@@ -520,13 +809,26 @@ impl Token {
             Token::Minus => 10,
             Token::Mul => 20,
             Token::Div => 20,
-            Token::Assign => 1,
-            Token::Eq => 2,
-            Token::Neq => 2,
-            Token::Lt => 2,
-            Token::Gt => 2,
-            Token::Le => 2,
-            Token::Ge => 2,
+            Token::Mod => 20,
+            Token::Shl => 8,
+            Token::Shr => 8,
+            Token::BitAnd => 7,
+            Token::Xor => 6,
+            Token::BitOr => 5,
+            Token::Assign
+            | Token::AddAssign
+            | Token::SubAssign
+            | Token::MulAssign
+            | Token::DivAssign
+            | Token::ModAssign => 1,
+            Token::Or => 2,
+            Token::And => 3,
+            Token::Eq => 4,
+            Token::Neq => 4,
+            Token::Lt => 4,
+            Token::Gt => 4,
+            Token::Le => 4,
+            Token::Ge => 4,
             _ => unreachable!("Precedence should only be called by binary operators"),
         }
     }
@@ -537,13 +839,26 @@ impl Token {
             | Token::Minus
             | Token::Mul
             | Token::Div
+            | Token::Mod
+            | Token::BitAnd
+            | Token::BitOr
+            | Token::Xor
+            | Token::Shl
+            | Token::Shr
             | Token::Eq
             | Token::Neq
             | Token::Lt
             | Token::Gt
             | Token::Le
-            | Token::Ge => true,
-            Token::Assign => false,
+            | Token::Ge
+            | Token::And
+            | Token::Or => true,
+            Token::Assign
+            | Token::AddAssign
+            | Token::SubAssign
+            | Token::MulAssign
+            | Token::DivAssign
+            | Token::ModAssign => false,
             _ => unreachable!("Method should only be called by binary operators"),
         }
     }
@@ -554,12 +869,40 @@ impl Token {
             Token::Minus => Some(BinaryOp::Sub),
             Token::Mul => Some(BinaryOp::Mul),
             Token::Div => Some(BinaryOp::Div),
+            Token::Mod => Some(BinaryOp::Mod),
+            Token::BitAnd => Some(BinaryOp::BitAnd),
+            Token::BitOr => Some(BinaryOp::BitOr),
+            Token::Xor => Some(BinaryOp::Xor),
+            Token::Shl => Some(BinaryOp::Shl),
+            Token::Shr => Some(BinaryOp::Shr),
             Token::Eq => Some(BinaryOp::Eq),
             Token::Neq => Some(BinaryOp::Neq),
             Token::Lt => Some(BinaryOp::Lt),
             Token::Gt => Some(BinaryOp::Gt),
             Token::Le => Some(BinaryOp::Le),
             Token::Ge => Some(BinaryOp::Ge),
+            Token::And => Some(BinaryOp::And),
+            Token::Or => Some(BinaryOp::Or),
+            _ => None,
+        }
+    }
+
+    /// Returns the operator of a compound assignment like `+=`.
+    pub fn to_compound_assign_op(&self) -> Option<BinaryOp> {
+        match self {
+            Token::AddAssign => Some(BinaryOp::Add),
+            Token::SubAssign => Some(BinaryOp::Sub),
+            Token::MulAssign => Some(BinaryOp::Mul),
+            Token::DivAssign => Some(BinaryOp::Div),
+            Token::ModAssign => Some(BinaryOp::Mod),
+            _ => None,
+        }
+    }
+
+    pub fn to_inc_dec_op(&self) -> Option<IncDecOp> {
+        match self {
+            Token::Inc => Some(IncDecOp::Inc),
+            Token::Dec => Some(IncDecOp::Dec),
             _ => None,
         }
     }
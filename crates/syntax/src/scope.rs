@@ -1 +1,347 @@
+//! Name resolution, i.e. finding the definition every identifier refers to.
+//!
+//! The scoping rules are the same as in code generation:
+//!
+//! - Builtin functions, global variables, extern functions and functions live
+//!   in the global scope, which is shared by all files of a program. Global
+//!   variables are declared in order, while functions are all declared before
+//!   any function body is resolved.
+//! - Function parameters live in their own scope, which the function body is
+//!   nested in.
+//! - Every block introduces a new scope, and so does a `for` statement for the
+//!   variables declared in its initializer.
+//! - A local variable is visible inside its own initializer.
 
+use std::{collections::HashMap, fmt::Display};
+
+use smol_str::SmolStr;
+
+use crate::{
+    ast::*,
+    diagnostics::{Diagnostic, Level},
+    span::{FileId, Span},
+    visitor::AstVisitor,
+};
+
+/// The ID of a definition, which indexes into [`Resolution::defs`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct DefId(pub u32);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DefKind {
+    Builtin,
+    Global,
+    Func,
+    Param,
+    Local,
+}
+
+/// Something an identifier can refer to.
+#[derive(Debug, Clone)]
+pub struct Def {
+    pub name: SmolStr,
+    pub kind: DefKind,
+    pub is_const: bool,
+    /// The span of the defining identifier, or `None` for builtins
+    pub span: Option<Span>,
+}
+
+#[derive(Debug)]
+pub struct ScopeError {
+    pub kind: ScopeErrorKind,
+    pub span: Span,
+}
+
+impl ScopeError {
+    /// Whether this is a hard error instead of a warning.
+    pub fn is_error(&self) -> bool {
+        !matches!(self.kind, ScopeErrorKind::Shadowing { .. })
+    }
+
+    /// Converts this error into a diagnostic for rendering.
+    pub fn to_diagnostic(&self) -> Diagnostic {
+        let level = if self.is_error() {
+            Level::Error
+        } else {
+            Level::Warning
+        };
+        Diagnostic::new(level, self.kind.to_string(), Some(self.span))
+    }
+}
+
+impl Display for ScopeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} at {:?}", self.kind, self.span)
+    }
+}
+
+#[derive(Debug)]
+pub enum ScopeErrorKind {
+    /// A name that isn't defined in any enclosing scope
+    Undefined(SmolStr),
+    /// A name defined twice in the same scope
+    Duplicate { name: SmolStr, prev: DefId },
+    /// A name hiding a definition in an enclosing scope. This is a warning.
+    Shadowing { name: SmolStr, prev: DefId },
+}
+
+impl Display for ScopeErrorKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ScopeErrorKind::Undefined(name) => write!(f, "cannot find `{}` in this scope", name),
+            ScopeErrorKind::Duplicate { name, .. } => write!(f, "`{}` is already declared", name),
+            ScopeErrorKind::Shadowing { name, .. } => {
+                write!(f, "`{}` shadows an earlier declaration", name)
+            }
+        }
+    }
+}
+
+/// The result of name resolution.
+#[derive(Debug, Default)]
+pub struct Resolution {
+    /// All definitions, indexed by [`DefId`]
+    pub defs: Vec<Def>,
+    /// The definition of every resolved identifier, keyed by its span. This
+    /// includes the identifiers that define names.
+    pub refs: HashMap<Span, DefId>,
+    /// Errors and warnings, in the order they are found
+    pub errors: Vec<ScopeError>,
+}
+
+impl Resolution {
+    pub fn def(&self, id: DefId) -> &Def {
+        &self.defs[id.0 as usize]
+    }
+
+    /// Returns the definition the identifier at `span` refers to.
+    pub fn def_at(&self, span: Span) -> Option<DefId> {
+        self.refs.get(&span).copied()
+    }
+
+    /// Returns the identifier containing the byte position `pos` of `file`
+    /// and its definition.
+    pub fn ident_at(&self, file: FileId, pos: usize) -> Option<(Span, DefId)> {
+        self.refs
+            .iter()
+            .find(|(span, _)| span.file == file && span.start() <= pos && pos < span.end())
+            .map(|(span, id)| (*span, *id))
+    }
+
+    /// Returns the spans of all identifiers referring to `id`, including the
+    /// one defining it, in file and source order.
+    pub fn refs_to(&self, id: DefId) -> Vec<Span> {
+        let mut spans: Vec<_> = self
+            .refs
+            .iter()
+            .filter(|(_, def)| **def == id)
+            .map(|(span, _)| *span)
+            .collect();
+        spans.sort_by_key(|span| (span.file, span.start()));
+        spans
+    }
+
+    pub fn has_errors(&self) -> bool {
+        self.errors.iter().any(|e| e.is_error())
+    }
+}
+
+/// Resolves the names inside a program. See the [module docs](self) for the
+/// scoping rules.
+pub struct Resolver {
+    res: Resolution,
+    scopes: Vec<HashMap<SmolStr, DefId>>,
+}
+
+impl Resolver {
+    pub fn new() -> Resolver {
+        Resolver {
+            res: Resolution::default(),
+            scopes: vec![HashMap::new()],
+        }
+    }
+
+    /// Declares a function provided by the runtime in the global scope.
+    pub fn declare_builtin(&mut self, name: &str) -> DefId {
+        self.declare(
+            Def {
+                name: name.into(),
+                kind: DefKind::Builtin,
+                is_const: true,
+                span: None,
+            },
+            Span::eof(),
+        )
+    }
+
+    pub fn resolve(self, program: &Program) -> Resolution {
+        self.resolve_all(std::slice::from_ref(program))
+    }
+
+    /// Resolves a program made of multiple files, given in the order their
+    /// global items are declared.
+    pub fn resolve_all(mut self, programs: &[Program]) -> Resolution {
+        self.visit_programs(programs);
+        self.res
+    }
+
+    fn visit_programs(&mut self, programs: &[Program]) {
+        for program in programs {
+            for decl in &program.decls {
+                if let Some(val) = &decl.val {
+                    self.visit_expr(val);
+                }
+                self.declare_ident(&decl.name, DefKind::Global, decl.is_const);
+            }
+        }
+        for func in programs.iter().flat_map(|p| &p.externs) {
+            self.declare_ident(&func.name, DefKind::Func, true);
+        }
+        for func in programs.iter().flat_map(|p| &p.funcs) {
+            self.declare_ident(&func.name, DefKind::Func, true);
+        }
+        for func in programs.iter().flat_map(|p| &p.funcs) {
+            self.visit_func(func);
+        }
+    }
+
+    fn declare_ident(&mut self, ident: &Ident, kind: DefKind, is_const: bool) -> DefId {
+        let def = Def {
+            name: ident.name.clone(),
+            kind,
+            is_const,
+            span: Some(ident.span),
+        };
+        let id = self.declare(def, ident.span);
+        self.res.refs.insert(ident.span, id);
+        id
+    }
+
+    fn declare(&mut self, def: Def, span: Span) -> DefId {
+        let id = DefId(self.res.defs.len() as u32);
+        let name = def.name.clone();
+        self.res.defs.push(def);
+
+        let (top, outer) = self.scopes.split_last_mut().unwrap();
+        if let Some(&prev) = top.get(&name) {
+            // The first definition stays visible
+            self.error(ScopeErrorKind::Duplicate { name, prev }, span);
+            return id;
+        }
+        if let Some(&prev) = outer.iter().rev().find_map(|scope| scope.get(&name)) {
+            self.error(
+                ScopeErrorKind::Shadowing {
+                    name: name.clone(),
+                    prev,
+                },
+                span,
+            );
+        }
+        self.scopes.last_mut().unwrap().insert(name, id);
+        id
+    }
+
+    fn lookup(&mut self, ident: &Ident) {
+        let def = self
+            .scopes
+            .iter()
+            .rev()
+            .find_map(|scope| scope.get(&ident.name));
+        match def {
+            Some(&id) => {
+                self.res.refs.insert(ident.span, id);
+            }
+            None => self.error(ScopeErrorKind::Undefined(ident.name.clone()), ident.span),
+        }
+    }
+
+    fn error(&mut self, kind: ScopeErrorKind, span: Span) {
+        self.res.errors.push(ScopeError { kind, span })
+    }
+
+    fn with_scope(&mut self, f: impl FnOnce(&mut Self)) {
+        self.scopes.push(HashMap::new());
+        f(self);
+        self.scopes.pop();
+    }
+}
+
+impl Default for Resolver {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl AstVisitor for Resolver {
+    type LExprResult = ();
+    type ExprResult = ();
+    type TyResult = ();
+    type StmtResult = ();
+    type ProgramResult = ();
+    type FuncResult = ();
+
+    fn default_lexpr_result() {}
+    fn default_expr_result() {}
+    fn default_ty_result() {}
+    fn default_stmt_result() {}
+    fn default_program_result() {}
+    fn default_func_result() {}
+
+    fn visit_program(&mut self, program: &Program) -> Self::ProgramResult {
+        self.visit_programs(std::slice::from_ref(program))
+    }
+
+    fn visit_func(&mut self, func: &FuncStmt) -> Self::FuncResult {
+        self.with_scope(|this| {
+            for param in &func.params {
+                this.visit_func_param(param);
+            }
+            this.visit_block_stmt(&func.body);
+        })
+    }
+
+    fn visit_func_param(&mut self, param: &FuncParam) -> Self::StmtResult {
+        self.declare_ident(&param.name, DefKind::Param, param.is_const);
+    }
+
+    fn visit_ident_expr(&mut self, expr: &Ident) -> Self::ExprResult {
+        self.lookup(expr)
+    }
+
+    fn visit_call_expr(&mut self, expr: &CallExpr) -> Self::ExprResult {
+        self.lookup(&expr.func);
+        for param in &expr.params {
+            self.visit_expr(param);
+        }
+    }
+
+    fn visit_block_stmt(&mut self, stmt: &BlockStmt) -> Self::StmtResult {
+        self.with_scope(|this| {
+            for substmt in &stmt.stmts {
+                this.visit_stmt(substmt);
+            }
+        })
+    }
+
+    fn visit_for_stmt(&mut self, stmt: &ForStmt) -> Self::StmtResult {
+        self.with_scope(|this| {
+            if let Some(init) = &stmt.init {
+                this.visit_stmt(init);
+            }
+            if let Some(cond) = &stmt.cond {
+                this.visit_expr(cond);
+            }
+            if let Some(step) = &stmt.step {
+                this.visit_expr(step);
+            }
+            this.visit_block_stmt(&stmt.body);
+        })
+    }
+
+    fn visit_decl_stmt(&mut self, stmt: &DeclStmt) -> Self::StmtResult {
+        self.declare_ident(&stmt.name, DefKind::Local, stmt.is_const);
+        if let Some(val) = &stmt.val {
+            self.visit_expr(val);
+        }
+    }
+}
@@ -0,0 +1,160 @@
+//! Programs made of multiple source files, connected by `import` items.
+
+use std::{
+    collections::HashSet,
+    path::{Component, Path, PathBuf},
+};
+
+use crate::{
+    ast::Program,
+    diagnostics::{Diagnostic, DiagnosticRenderer, LineIndex},
+    parser::err::{ParseError, ParseErrorKind},
+    span::FileId,
+};
+
+/// A source file inside a [`SourceMap`].
+pub struct SourceFile {
+    /// The path of this file, which imports inside it are relative to
+    pub name: String,
+    pub src: String,
+    pub lines: LineIndex,
+}
+
+/// Owns all source files of a program. Spans point into these files by their
+/// [`FileId`].
+#[derive(Default)]
+pub struct SourceMap {
+    files: Vec<SourceFile>,
+}
+
+impl SourceMap {
+    pub fn new() -> SourceMap {
+        SourceMap { files: vec![] }
+    }
+
+    /// Adds a file to this source map. The first file added gets the default
+    /// file ID, which is the one used when parsing a single file.
+    ///
+    /// `.` and `..` inside the name are normalized, so that the same file
+    /// always gets the same name no matter where it's imported from.
+    pub fn add(&mut self, name: impl Into<String>, src: impl Into<String>) -> FileId {
+        let src = src.into();
+        let id = FileId(self.files.len() as u32);
+        self.files.push(SourceFile {
+            name: normalize(Path::new(&name.into()))
+                .to_string_lossy()
+                .into_owned(),
+            lines: LineIndex::new(&src),
+            src,
+        });
+        id
+    }
+
+    pub fn file(&self, id: FileId) -> &SourceFile {
+        &self.files[id.0 as usize]
+    }
+
+    /// Finds a file by its name.
+    pub fn find(&self, name: &str) -> Option<FileId> {
+        self.files
+            .iter()
+            .position(|file| file.name == name)
+            .map(|idx| FileId(idx as u32))
+    }
+
+    pub fn files(&self) -> impl Iterator<Item = (FileId, &SourceFile)> {
+        self.files
+            .iter()
+            .enumerate()
+            .map(|(idx, file)| (FileId(idx as u32), file))
+    }
+
+    /// Renders the diagnostic against the file its span points into.
+    /// Diagnostics without a span are rendered against the first file.
+    pub fn render(&self, diag: &Diagnostic) -> String {
+        let file = self.file(diag.span.map_or_else(FileId::default, |span| span.file));
+        DiagnosticRenderer::new(&file.name, &file.src).render(diag)
+    }
+
+    /// Parses the file `main` and all files it imports, directly or not.
+    /// Imported files that aren't in the source map yet are read by `load`.
+    ///
+    /// Every file is only parsed once, even if it's imported many times or in
+    /// a cycle. The returned programs are ordered so that imported files come
+    /// before the files importing them, with `main` being the last one.
+    pub fn parse_with_imports(
+        &mut self,
+        main: FileId,
+        mut load: impl FnMut(&Path) -> std::io::Result<String>,
+    ) -> (Vec<Program>, Vec<ParseError>) {
+        let mut programs = vec![];
+        let mut errors = vec![];
+        let mut visited = HashSet::new();
+        self.parse_file(main, &mut load, &mut visited, &mut programs, &mut errors);
+        (programs, errors)
+    }
+
+    fn parse_file(
+        &mut self,
+        file: FileId,
+        load: &mut dyn FnMut(&Path) -> std::io::Result<String>,
+        visited: &mut HashSet<FileId>,
+        programs: &mut Vec<Program>,
+        errors: &mut Vec<ParseError>,
+    ) {
+        if !visited.insert(file) {
+            return;
+        }
+        let (program, parse_errors) = crate::parse_file(&self.file(file).src, file);
+        errors.extend(parse_errors);
+
+        for import in &program.imports {
+            let path = self.import_path(file, &import.path);
+            let name = path.to_string_lossy();
+            let imported = match self.find(&name) {
+                Some(id) => id,
+                None => match load(&path) {
+                    Ok(src) => self.add(name.into_owned(), src),
+                    Err(e) => {
+                        errors.push(ParseError::new_span(
+                            ParseErrorKind::ImportFailed {
+                                path: import.path.clone(),
+                                reason: e.to_string(),
+                            },
+                            import.span,
+                        ));
+                        continue;
+                    }
+                },
+            };
+            self.parse_file(imported, load, visited, programs, errors);
+        }
+        programs.push(program);
+    }
+
+    /// Returns the path of a file imported by `importer`.
+    fn import_path(&self, importer: FileId, path: &str) -> PathBuf {
+        let dir = Path::new(&self.file(importer).name)
+            .parent()
+            .unwrap_or_else(|| Path::new(""));
+        normalize(&dir.join(path))
+    }
+}
+
+/// Removes `.` and resolves `..` inside the path, without looking at the
+/// file system.
+fn normalize(path: &Path) -> PathBuf {
+    let mut res = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir
+                if matches!(res.components().next_back(), Some(Component::Normal(_))) =>
+            {
+                res.pop();
+            }
+            c => res.push(c),
+        }
+    }
+    res
+}
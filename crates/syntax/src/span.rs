@@ -1,11 +1,21 @@
 use std::{fmt::Debug, ops::Index};
 
+/// The ID of a source file inside a [`SourceMap`](crate::source_map::SourceMap).
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash, PartialOrd, Ord, Default)]
+#[cfg_attr(feature = "serde_impl", derive(serde::Serialize, serde::Deserialize))]
+pub struct FileId(pub u32);
+
 /// A Span is the information of a piece of source code inside a file.
 ///
 /// `Span`s are only meaningful when indexing the file it is originated from.
-#[derive(Clone, Copy, Eq, PartialEq)]
-#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Clone, Copy, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "serde_impl", derive(serde::Serialize, serde::Deserialize))]
 pub struct Span {
+    /// The file this span is in. Programs made of a single file only use the
+    /// default ID.
+    #[cfg_attr(feature = "serde_impl", serde(default))]
+    pub file: FileId,
+
     /// The start index (in bytes or other meaningful item index)
     /// in the file of this span
     pub idx: usize,
@@ -15,7 +25,7 @@ pub struct Span {
 }
 
 pub const DUMMY_SPAN: Span = Span {
-    // ctx: usize::max_value(),
+    file: FileId(0),
     idx: 0,
     len: 0,
 };
@@ -30,21 +40,36 @@ impl Span {
     }
 
     pub fn new(idx: usize, len: usize) -> Span {
-        Span { idx, len }
+        Span {
+            file: FileId::default(),
+            idx,
+            len,
+        }
     }
 
     pub fn new_idx(lo: usize, hi: usize) -> Span {
         let (lo, hi) = if lo > hi { (hi, lo) } else { (lo, hi) };
         let len = hi - lo;
-        Span { idx: lo, len }
+        Span::new(lo, len)
     }
 
     pub const fn eof() -> Span {
         Span {
+            file: FileId(0),
             idx: usize::max_value(),
             len: 0,
         }
     }
+
+    /// Returns the same span inside the given file.
+    pub fn with_file(self, file: FileId) -> Span {
+        Span { file, ..self }
+    }
+
+    /// Whether this span points at the end of its file.
+    pub fn is_eof(&self) -> bool {
+        self.idx == usize::MAX
+    }
 }
 
 impl std::ops::Add for Span {
@@ -53,7 +78,7 @@ impl std::ops::Add for Span {
     fn add(self, rhs: Self) -> Self::Output {
         let start = std::cmp::min(self.start(), rhs.start());
         let end = std::cmp::max(self.end(), rhs.end());
-        Span::new_idx(start, end)
+        Span::new_idx(start, end).with_file(self.file)
     }
 }
 
@@ -65,6 +90,9 @@ impl std::ops::AddAssign for Span {
 
 impl Debug for Span {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.file != FileId::default() {
+            write!(f, "#{}", self.file.0)?;
+        }
         write!(f, "[{}, {})", self.idx, self.idx + self.len)
     }
 }
@@ -0,0 +1,698 @@
+#![cfg(test)]
+
+use crate::{
+    ast::*,
+    diagnostics::{DiagnosticRenderer, LineCol, LineIndex},
+    parse, parse_recovering,
+};
+
+#[test]
+fn line_index_maps_offsets() {
+    let src = "fn main() -> int {\n    return 0;\n}";
+    let index = LineIndex::new(src);
+    assert_eq!(index.line_count(), 3);
+    assert_eq!(index.line_col(src, 0), LineCol { line: 0, col: 0 });
+    assert_eq!(index.line_col(src, 23), LineCol { line: 1, col: 4 });
+    assert_eq!(index.offset_of(src, LineCol { line: 1, col: 4 }), 23);
+    // Offsets past the end of file are clamped
+    assert_eq!(index.line_col(src, usize::MAX), LineCol { line: 2, col: 1 });
+}
+
+#[test]
+fn render_parse_error() {
+    let src = "fn main() -> int {\n    return 0\n}\n";
+    let err = parse(src).unwrap_err();
+    let rendered = DiagnosticRenderer::new("main.c0", src).render(&err.to_diagnostic());
+    assert_eq!(
+        rendered,
+        "error: expected Semicolon\n --> main.c0:3:1\n  |\n3 | }\n  | ^\n"
+    );
+}
+
+#[test]
+fn render_eof_error() {
+    let src = "fn main() -> int {\n    return 0;\n";
+    let err = parse(src).unwrap_err();
+    let rendered = DiagnosticRenderer::new("main.c0", src).render(&err.to_diagnostic());
+    assert!(rendered.contains(" --> main.c0:3:1\n"), "{}", rendered);
+}
+
+#[test]
+fn recover_from_multiple_errors() {
+    let src = r"
+    fn foo(a: int) -> int {
+        let x: int = a +;
+        if x > {
+            x = 1;
+        }
+        return x
+    }
+    fn main() -> int {
+        return foo(1);
+    }
+    ";
+    let (program, errors) = crate::parse_recovering(src);
+    assert_eq!(errors.len(), 3, "{:?}", errors);
+    assert_eq!(program.funcs.len(), 2);
+    assert!(matches!(program.funcs[0].body.stmts[0], Stmt::Error(_)));
+    assert!(matches!(program.funcs[0].body.stmts[1], Stmt::Error(_)));
+    assert!(matches!(program.funcs[1].body.stmts[0], Stmt::Return(_)));
+}
+
+#[test]
+fn recover_at_import_after_unclosed_block() {
+    let src = r#"
+    fn foo() -> void {
+        let x: int = 1 +
+    import "lib.c0";
+    fn main() -> void {}
+    "#;
+    let (program, errors) = crate::parse_recovering(src);
+    assert!(!errors.is_empty());
+    assert_eq!(program.imports.len(), 1);
+    assert_eq!(program.funcs.len(), 2);
+}
+
+#[test]
+fn visitor_walks_whole_tree() {
+    use crate::visitor::AstVisitor;
+
+    /// Collects the names of all identifiers used inside expressions
+    #[derive(Default)]
+    struct IdentCollector(Vec<String>);
+
+    impl AstVisitor for IdentCollector {
+        type LExprResult = ();
+        type ExprResult = ();
+        type TyResult = ();
+        type StmtResult = ();
+        type ProgramResult = ();
+        type FuncResult = ();
+
+        fn default_lexpr_result() {}
+        fn default_expr_result() {}
+        fn default_ty_result() {}
+        fn default_stmt_result() {}
+        fn default_program_result() {}
+        fn default_func_result() {}
+
+        fn visit_ident_expr(&mut self, expr: &Ident) {
+            self.0.push(expr.name.to_string());
+        }
+    }
+
+    let src = r"
+    let g: int = a;
+    fn f(x: int) -> int {
+        for (let i: int = b; i < c; i = i + 1) {
+            if d { e = f[0]; } else { g(h as int); }
+        }
+        while -k { break; }
+        return x;
+    }
+    ";
+    let program = parse(src).unwrap();
+    let mut collector = IdentCollector::default();
+    collector.visit_program(&program);
+    assert_eq!(
+        collector.0,
+        vec!["a", "b", "i", "c", "i", "i", "d", "e", "f", "h", "k", "x"]
+    );
+}
+
+#[test]
+fn visitor_mut_rewrites_tree() {
+    use crate::visitor::{walk_expr_mut, AstVisitorMut};
+
+    /// Folds additions of two integer literals
+    struct AddFolder;
+
+    impl AstVisitorMut for AddFolder {
+        fn visit_expr(&mut self, expr: &mut Expr) {
+            walk_expr_mut(self, expr);
+            if let Expr::Binary(BinaryExpr {
+                op: BinaryOp::Add,
+                lhs,
+                rhs,
+                span,
+            }) = expr
+            {
+                if let (Expr::Literal(lhs), Expr::Literal(rhs)) = (&**lhs, &**rhs) {
+                    if let (LiteralKind::Integer(l), LiteralKind::Integer(r)) =
+                        (&lhs.kind, &rhs.kind)
+                    {
+                        *expr = Expr::Literal(LiteralExpr {
+                            span: *span,
+                            kind: LiteralKind::Integer(l + r),
+                        });
+                    }
+                }
+            }
+        }
+    }
+
+    let src = r"
+    fn f() -> int {
+        return 1 + 2 + 3;
+    }
+    ";
+    let mut program = parse(src).unwrap();
+    AddFolder.visit_program(&mut program);
+    match &program.funcs[0].body.stmts[0] {
+        Stmt::Return(ReturnStmt { val: Some(val), .. }) => {
+            assert!(matches!(
+                &**val,
+                Expr::Literal(LiteralExpr {
+                    kind: LiteralKind::Integer(6),
+                    ..
+                })
+            ))
+        }
+        other => panic!("expected a return statement, got {:?}", other),
+    }
+}
+
+/// Dumps the tree of `program` without spans, for comparing trees parsed from
+/// different source code.
+fn dump_without_spans(program: &Program) -> String {
+    let dump = crate::ast::formatter::TreeDump(program).to_string();
+    dump.lines()
+        .map(|line| match line.rfind(" [") {
+            Some(idx) if line.ends_with(')') => &line[..idx],
+            _ => line,
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+#[test]
+fn pretty_print_round_trip() {
+    let src = r#"
+    const N: int = 10; let g: [int; 3];
+    fn f(const x: int, a: [[int;2];3]) -> double {
+        let y: int = -(-x) + (1 - (2 - 3)) * 4 % 5 << 1 & 7 | 8 ^ 9;
+        y = a[0][1] = (y = 2) + 1;
+        if !(x > 1) && (x < 2 || x == 3) { putstr("a\"b\n\t\\"); }
+        else if x { ; } else { return 1.5e-7 + 1.0e100 + -(x as double); }
+        for (;;) { break; }
+        for (let i: int = 0; i < N; i = i + 1) { continue; }
+        while ~y as bool { {} }
+        return (-x as double) * 2.0;
+    }
+    fn main() -> void { f(1, (g)); }
+    "#;
+    let program = parse(src).unwrap();
+    let printed = program.to_string();
+    let reparsed = parse(&printed).unwrap();
+    assert_eq!(dump_without_spans(&program), dump_without_spans(&reparsed));
+    assert_eq!(printed, reparsed.to_string());
+
+    assert_eq!(
+        printed,
+        r#"const N: int = 10;
+let g: [int; 3];
+
+fn f(const x: int, a: [[int; 2]; 3]) -> double {
+    let y: int = -(-x) + (1 - (2 - 3)) * 4 % 5 << 1 & 7 | 8 ^ 9;
+    y = a[0][1] = (y = 2) + 1;
+    if !(x > 1) && (x < 2 || x == 3) {
+        putstr("a\"b\n\t\\");
+    } else if x {
+        ;
+    } else {
+        return 1.5e-7 + 1.0e100 + -(x as double);
+    }
+    for (;;) {
+        break;
+    }
+    for (let i: int = 0; i < N; i = i + 1) {
+        continue;
+    }
+    while ~y as bool {
+        {}
+    }
+    return -x as double * 2.0;
+}
+
+fn main() -> void {
+    f(1, g);
+}
+"#
+    );
+}
+
+#[test]
+fn pretty_print_indent_and_tree_dump() {
+    use crate::ast::formatter::{PrettyPrinter, TreeDump};
+
+    let program = parse("fn f() -> int { if true { return 1; } return 0; }").unwrap();
+    assert_eq!(
+        PrettyPrinter::new("\t").print_program(&program),
+        "fn f() -> int {\n\tif true {\n\t\treturn 1;\n\t}\n\treturn 0;\n}\n"
+    );
+    assert_eq!(
+        TreeDump(&program).to_string(),
+        "Program
+  Func [0, 49)
+    Ident f [3, 4)
+    Ty int [10, 13)
+    Block [14, 49)
+      If [16, 37)
+        Literal true [19, 23)
+        Block [24, 37)
+          Return [26, 35)
+            Literal 1 [33, 34)
+      Return [38, 47)
+        Literal 0 [45, 46)
+"
+    );
+}
+
+#[test]
+fn lossless_lexer_keeps_trivia() {
+    use crate::lexer::{lossless_lexer, LosslessToken};
+
+    let src = "let x: int = 1; // one\n  // two\r\nx = 2;// three";
+    let tokens: Vec<_> = lossless_lexer(src).collect();
+    let text: String = tokens
+        .iter()
+        .map(|(_, span)| &src[span.start()..span.end()])
+        .collect();
+    assert_eq!(text, src);
+
+    let comments: Vec<_> = tokens
+        .iter()
+        .filter(|(tok, _)| matches!(tok, LosslessToken::Comment))
+        .map(|(_, span)| &src[span.start()..span.end()])
+        .collect();
+    assert_eq!(comments, vec!["// one", "// two\r", "// three"]);
+}
+
+#[test]
+fn format_keeps_comments() {
+    use crate::ast::formatter::PrettyPrinter;
+
+    let src = "// Global counter
+let  counter:int=0; // trailing global
+
+// The main function
+fn main()->int{
+  // leading
+  let x:int=1;   // one
+  if x>0 {counter=counter+1; // inc
+  }
+  else {
+     // only a comment
+  }
+  foo(1, // arg
+     2);
+  return x;  // done
+  // before end
+} // after fn
+// eof comment
+";
+    let expected = "// Global counter
+let counter: int = 0; // trailing global
+
+// The main function
+fn main() -> int {
+    // leading
+    let x: int = 1; // one
+    if x > 0 {
+        counter = counter + 1; // inc
+    } else {
+        // only a comment
+    }
+    foo(1, 2); // arg
+    return x; // done
+    // before end
+} // after fn
+// eof comment
+";
+    let printer = PrettyPrinter::default();
+    let formatted = printer.print_program_with_comments(&parse(src).unwrap(), src);
+    assert_eq!(formatted, expected);
+    // Formatting is idempotent
+    let reformatted = printer.print_program_with_comments(&parse(expected).unwrap(), expected);
+    assert_eq!(reformatted, expected);
+}
+
+#[test]
+fn format_keeps_item_order() {
+    use crate::ast::formatter::PrettyPrinter;
+
+    let src = "let a: int = 1;
+fn f() -> void {
+    // inside f
+    return;
+} // trailing f
+let g: int = 2; // trailing g
+const h: int = 3;
+// before main
+fn main() -> void {}
+";
+    let expected = "let a: int = 1;
+
+fn f() -> void {
+    // inside f
+    return;
+} // trailing f
+
+let g: int = 2; // trailing g
+const h: int = 3;
+
+// before main
+fn main() -> void {}
+";
+    let printer = PrettyPrinter::default();
+    let program = parse(src).unwrap();
+    assert_eq!(printer.print_program_with_comments(&program, src), expected);
+    let reformatted = printer.print_program_with_comments(&parse(expected).unwrap(), expected);
+    assert_eq!(reformatted, expected);
+
+    // The tree dump follows the source order too
+    let dump = dump_without_spans(&program);
+    let f = dump.find("Func").unwrap();
+    assert!(
+        dump[..f].contains("Ident a") && dump[f..].contains("Ident g"),
+        "{}",
+        dump
+    );
+}
+
+#[cfg(feature = "serde_impl")]
+#[test]
+fn serialize_tokens_and_ast() {
+    use crate::lexer::{spanned_lexer, SpannedToken};
+
+    let src = "fn main() -> int { return f(1.5, \"s\") as int; }";
+    let tokens: Vec<_> = spanned_lexer(src)
+        .map(|token| {
+            let (token, span) = token.unwrap();
+            SpannedToken { token, span }
+        })
+        .collect();
+    let json = serde_json::to_value(&tokens).unwrap();
+    assert_eq!(
+        json[1],
+        serde_json::json!({ "token": { "Ident": "main" }, "span": { "file": 0, "idx": 3, "len": 4 } })
+    );
+    let tokens_back: Vec<SpannedToken> = serde_json::from_value(json.clone()).unwrap();
+    assert_eq!(serde_json::to_value(&tokens_back).unwrap(), json);
+
+    let program = parse(src).unwrap();
+    let json = serde_json::to_value(&program).unwrap();
+    assert_eq!(json["funcs"][0]["name"]["name"], "main");
+    let program_back: Program = serde_json::from_value(json.clone()).unwrap();
+    assert_eq!(serde_json::to_value(&program_back).unwrap(), json);
+}
+
+#[test]
+fn lex_literals_and_block_comments() {
+    use crate::{
+        lexer::{lossless_lexer, spanned_lexer, LexErrorKind, LosslessToken},
+        Token,
+    };
+
+    let src = "0x1F 0B1010_1010 0o17 1_000_000 1_0.2_5 /* a /* b */ c */ 18446744073709551616 x";
+    let tokens: Vec<_> = spanned_lexer(src).collect();
+    let uints: Vec<_> = tokens
+        .iter()
+        .filter_map(|t| t.as_ref().ok()?.0.get_uint())
+        .collect();
+    assert_eq!(uints, vec![0x1f, 0b1010_1010, 0o17, 1_000_000]);
+    assert!(matches!(tokens[4], Ok((Token::FloatLiteral(f), _)) if f == 10.25));
+
+    // The overflowing literal is reported with its own span, and the nested
+    // comment before it is skipped entirely
+    let err = tokens[5].as_ref().unwrap_err();
+    assert_eq!(err.kind, LexErrorKind::IntOverflow);
+    assert_eq!(
+        &src[err.span.start()..err.span.end()],
+        "18446744073709551616"
+    );
+    assert!(matches!(&tokens[6], Ok((Token::Ident(x), _)) if x == "x"));
+    assert_eq!(tokens.len(), 7);
+
+    // Literals are signed, so anything above `i64::MAX` overflows
+    let large =
+        "9223372036854775807 9223372036854775808 18446744073709551615 0xFFFF_FFFF_FFFF_FFFF 0x_";
+    let results: Vec<_> = spanned_lexer(large)
+        .map(|t| t.map(|(tok, _)| tok.get_uint()).map_err(|e| e.kind))
+        .collect();
+    assert_eq!(
+        results,
+        vec![
+            Ok(Some(i64::MAX as u64)),
+            Err(LexErrorKind::IntOverflow),
+            Err(LexErrorKind::IntOverflow),
+            Err(LexErrorKind::IntOverflow),
+            Err(LexErrorKind::MissingDigits),
+        ]
+    );
+
+    let comments: Vec<_> = lossless_lexer(src)
+        .filter(|(tok, _)| matches!(tok, LosslessToken::Comment))
+        .map(|(_, span)| &src[span.start()..span.end()])
+        .collect();
+    assert_eq!(comments, vec!["/* a /* b */ c */"]);
+
+    let src = "x /* a /* b */";
+    let tokens: Vec<_> = spanned_lexer(src).collect();
+    assert_eq!(tokens.len(), 2);
+    let err = tokens[1].as_ref().unwrap_err();
+    assert_eq!(err.kind, LexErrorKind::UnterminatedComment);
+    assert_eq!(err.span.end(), src.len());
+}
+
+#[test]
+fn report_lex_errors() {
+    use crate::{
+        lexer::{spanned_lexer, LexErrorKind},
+        parser::err::ParseErrorKind,
+    };
+
+    let src = r#"@ "a\q" '\n' 'ab' "open"#;
+    let errors: Vec<_> = spanned_lexer(src)
+        .filter_map(|t| t.err())
+        .map(|e| (e.kind, &src[e.span.start()..e.span.end()]))
+        .collect();
+    assert_eq!(
+        errors,
+        vec![
+            (LexErrorKind::InvalidChar, "@"),
+            (LexErrorKind::BadEscape, r#""a\q""#),
+            (LexErrorKind::InvalidCharLiteral, "'ab'"),
+            (LexErrorKind::UnterminatedString, "\"open"),
+        ]
+    );
+
+    // Malformed literals are reported once, without cascading syntax errors
+    let src = r#"
+    fn main() -> void {
+        let a: int = 99999999999999999999;
+        putstr("bad \escape");
+        let b: int = 1 # 2;
+    }
+    "#;
+    let (_, errors) = parse_recovering(src);
+    let kinds: Vec<_> = errors.iter().map(|e| &e.kind).collect();
+    assert!(matches!(
+        kinds[..],
+        [
+            ParseErrorKind::Lex(LexErrorKind::IntOverflow),
+            ParseErrorKind::Lex(LexErrorKind::BadEscape),
+            ParseErrorKind::Lex(LexErrorKind::InvalidChar),
+            ..
+        ]
+    ));
+    let err = parse(src).unwrap_err();
+    assert!(matches!(
+        err.kind,
+        ParseErrorKind::Lex(LexErrorKind::IntOverflow)
+    ));
+
+    // Invalid characters are dropped, and the syntax error they cause is
+    // reported only once
+    let src = "fn main() -> void { let t: int = @; }";
+    let (_, errors) = parse_recovering(src);
+    assert_eq!(errors.len(), 1, "{:?}", errors);
+    assert!(matches!(
+        errors[0].kind,
+        ParseErrorKind::Lex(LexErrorKind::InvalidChar)
+    ));
+    assert_eq!(errors[0].span.unwrap().start(), src.find('@').unwrap());
+}
+
+#[test]
+fn resolve_names() {
+    use crate::{
+        scope::{DefKind, Resolver, ScopeErrorKind},
+        span::FileId,
+    };
+
+    let src = r#"
+    let g: int = 1;
+    fn main() -> void {
+        let x: int = g;
+        {
+            let g: int = x;
+            putint(g + f(x));
+        }
+        let x: int = 2;
+        putint(y);
+    }
+    fn f(a: int) -> int { return a; }
+    "#;
+    let program = parse(src).unwrap();
+    let mut resolver = Resolver::new();
+    resolver.declare_builtin("putint");
+    let res = resolver.resolve(&program);
+
+    let at = |s: &str, nth: usize| {
+        let pos = src.match_indices(s).nth(nth).unwrap().0;
+        res.ident_at(FileId::default(), pos)
+            .map(|(_, id)| res.def(id))
+    };
+    // The global `g` is used in the first initializer, then shadowed
+    assert_eq!(at("g;", 0).unwrap().kind, DefKind::Global);
+    assert_eq!(at("g + ", 0).unwrap().kind, DefKind::Local);
+    assert_eq!(at("putint", 0).unwrap().kind, DefKind::Builtin);
+    // Functions can be called before they are defined
+    assert_eq!(at("f(x)", 0).unwrap().kind, DefKind::Func);
+    assert_eq!(at("a;", 0).unwrap().kind, DefKind::Param);
+
+    let (_, first_x) = res
+        .ident_at(FileId::default(), src.find("x: int").unwrap())
+        .unwrap();
+    assert_eq!(res.refs_to(first_x).len(), 3);
+
+    let kinds: Vec<_> = res.errors.iter().map(|e| &e.kind).collect();
+    assert!(matches!(
+        kinds[..],
+        [
+            ScopeErrorKind::Shadowing { name: g, .. },
+            ScopeErrorKind::Duplicate { name: x, prev },
+            ScopeErrorKind::Undefined(y),
+        ] if g == "g" && x == "x" && *prev == first_x && y == "y"
+    ));
+    assert!(res.has_errors());
+}
+
+#[test]
+fn parse_imports_into_source_map() {
+    use crate::{parser::err::ParseErrorKind, source_map::SourceMap, span::FileId};
+    use std::{collections::HashMap, io, path::Path};
+
+    let disk: HashMap<&str, &str> = vec![
+        (
+            "src/lib/math.c0",
+            "import \"../main.c0\";\nfn one() -> int { return 1; }",
+        ),
+        (
+            "src/util.c0",
+            "import \"lib/math.c0\";\nfn two() -> int { return one() + one() }",
+        ),
+    ]
+    .into_iter()
+    .collect();
+    let load = |path: &Path| {
+        let name = path.to_str().unwrap();
+        disk.get(name)
+            .map(|src| src.to_string())
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "file not found"))
+    };
+
+    let mut files = SourceMap::new();
+    let main_src =
+        "import \"./util.c0\";\nimport \"lib/math.c0\";\nimport \"nope.c0\";\nfn main() -> void {}";
+    let main = files.add("./src/main.c0", main_src);
+    let (programs, errors) = files.parse_with_imports(main, load);
+
+    // Every file is parsed once, with imported files before their importers
+    let names: Vec<_> = files.files().map(|(_, file)| file.name.as_str()).collect();
+    assert_eq!(names, vec!["src/main.c0", "src/util.c0", "src/lib/math.c0"]);
+    let funcs: Vec<_> = programs
+        .iter()
+        .map(|p| (p.funcs[0].name.name.as_str(), p.funcs[0].span.file))
+        .collect();
+    assert_eq!(
+        funcs,
+        vec![("one", FileId(2)), ("two", FileId(1)), ("main", FileId(0))]
+    );
+    assert_eq!(
+        programs[2].to_string().lines().next(),
+        Some("import \"./util.c0\";")
+    );
+
+    // The missing semicolon is reported inside `util.c0`
+    assert_eq!(errors.len(), 2);
+    let span = errors[0].span.unwrap();
+    assert_eq!(span.file, FileId(1));
+    let rendered = files.render(&errors[0].to_diagnostic());
+    assert!(rendered.contains("--> src/util.c0:2:"), "{}", rendered);
+    assert!(matches!(
+        &errors[1].kind,
+        ParseErrorKind::ImportFailed { path, .. } if path == "nope.c0"
+    ));
+    assert_eq!(errors[1].span.unwrap().file, FileId(0));
+}
+
+#[test]
+fn parse_extern_fn() {
+    let src = "extern fn log(x: int, const y: double) -> void;\nlet a: int;\nfn main() -> void { log(a, 1.0); }\n";
+    let program = parse(src).unwrap();
+    assert_eq!(program.externs.len(), 1);
+    let log = &program.externs[0];
+    assert_eq!(log.name.name, "log");
+    assert_eq!(log.params.len(), 2);
+    assert_eq!(&src[log.span.start()..log.span.end()], &src[..47]);
+
+    // Items keep their source order
+    assert_eq!(
+        program.to_string(),
+        "extern fn log(x: int, const y: double) -> void;\n\nlet a: int;\n\nfn main() -> void {\n    log(a, 1.0);\n}\n"
+    );
+
+    // Comments stay with their externs when formatting
+    let src = "extern fn host() -> void; // host fn\nlet g: int = 1;\n// before log\nextern fn log(x: int) -> void;\n";
+    let formatted = crate::ast::formatter::PrettyPrinter::default()
+        .print_program_with_comments(&parse(src).unwrap(), src);
+    assert_eq!(
+        formatted,
+        "extern fn host() -> void; // host fn\n\nlet g: int = 1;\n\n// before log\nextern fn log(x: int) -> void;\n"
+    );
+
+    // A missing semicolon doesn't swallow the next item
+    let (program, errors) = parse_recovering("extern fn f() -> int\nfn main() -> void {}\n");
+    assert_eq!(errors.len(), 1);
+    assert!(program.externs.is_empty());
+    assert_eq!(program.funcs[0].name.name, "main");
+}
+
+#[test]
+fn parse_compound_assign_and_inc_dec() {
+    let src = "fn f() -> void { a[i++] *= 2; x = -(--y) + z--; i %= j += 1; ++a[0]; }";
+    let program = parse(src).unwrap();
+    let printed = program.to_string();
+    assert_eq!(
+        printed,
+        "fn f() -> void {\n    a[i++] *= 2;\n    x = -(--y) + z--;\n    i %= j += 1;\n    ++a[0];\n}\n"
+    );
+    let reparsed = parse(&printed).unwrap();
+    assert_eq!(dump_without_spans(&program), dump_without_spans(&reparsed));
+
+    let dump = dump_without_spans(&program);
+    assert!(dump.contains("Assign Mul"));
+    assert!(dump.contains("IncDec Inc postfix"));
+    assert!(dump.contains("IncDec Dec prefix"));
+    // Compound assignments are right associative, like plain ones
+    let stmt = &program.funcs[0].body.stmts[2];
+    match stmt {
+        Stmt::Expr(Expr::Assign(e)) => {
+            assert!(matches!(e.op, Some(BinaryOp::Mod)));
+            assert!(matches!(&*e.rhs, Expr::Assign(rhs) if matches!(rhs.op, Some(BinaryOp::Add))));
+        }
+        other => panic!("expected an assignment, got {:?}", other),
+    }
+}
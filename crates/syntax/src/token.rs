@@ -1,22 +1,141 @@
 pub mod formatter;
 
-use logos::{Lexer, Logos};
+use logos::{Filter, Lexer, Logos};
 use smol_str::SmolStr;
 
+use crate::lexer::LexErrorKind;
+
+/// Replaces the escape sequences in the contents of a string or character
+/// literal, or returns `None` if there's an unknown one.
+fn unescape(s: &str) -> Option<String> {
+    let mut res = String::with_capacity(s.len());
+    let mut chars = s.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            res.push(c);
+            continue;
+        }
+        res.push(match chars.next()? {
+            'r' => '\r',
+            'n' => '\n',
+            't' => '\t',
+            c @ '\\' | c @ '/' | c @ '"' | c @ '\'' => c,
+            _ => return None,
+        });
+    }
+    Some(res)
+}
+
 fn parse_string_literal(i: &mut Lexer<Token>) -> Option<String> {
-    unescape::unescape(&i.slice()[1..i.slice().len() - 1])
-    // Some(i.slice().into())
+    let res = unescape(&i.slice()[1..i.slice().len() - 1]);
+    if res.is_none() {
+        i.extras = Some(LexErrorKind::BadEscape);
+    }
+    res
+}
+
+/// Reports a string literal that reaches the end of file without its closing
+/// quote.
+fn unterminated_string(i: &mut Lexer<Token>) -> Option<String> {
+    i.extras = Some(LexErrorKind::UnterminatedString);
+    None
+}
+
+/// Parses an integer literal with an optional radix prefix and `_` digit
+/// separators.
+fn parse_int_literal(i: &mut Lexer<Token>) -> Option<u64> {
+    let s = i.slice();
+    let (digits, radix) = match s.get(..2) {
+        Some("0x") | Some("0X") => (&s[2..], 16),
+        Some("0o") | Some("0O") => (&s[2..], 8),
+        Some("0b") | Some("0B") => (&s[2..], 2),
+        _ => (s, 10),
+    };
+    let digits = digits.replace('_', "");
+    if digits.is_empty() {
+        i.extras = Some(LexErrorKind::MissingDigits);
+        return None;
+    }
+    // Integers are signed 64-bit values, so larger literals overflow even if
+    // they fit in a `u64`
+    let res = u64::from_str_radix(&digits, radix)
+        .ok()
+        .filter(|&val| val <= i64::MAX as u64);
+    if res.is_none() {
+        // All digits are valid at this point, so the literal must be too large
+        i.extras = Some(LexErrorKind::IntOverflow);
+    }
+    res
+}
+
+fn parse_float_literal(i: &mut Lexer<Token>) -> Option<f64> {
+    i.slice().replace('_', "").parse().ok()
+}
+
+/// Skips a (possibly nested) block comment, or reports it if it's never
+/// closed.
+fn skip_block_comment(i: &mut Lexer<Token>) -> Filter<LexErrorKind> {
+    // The opening `/*` is already consumed
+    match block_comment_len(&i.source()[i.span().start..]) {
+        Some(len) => {
+            i.bump(len - 2);
+            Filter::Skip
+        }
+        None => {
+            i.bump(i.remainder().len());
+            Filter::Emit(LexErrorKind::UnterminatedComment)
+        }
+    }
+}
+
+/// Returns the length of the block comment at the start of `s`, including
+/// nested comments, or `None` if it isn't closed.
+pub(crate) fn block_comment_len(s: &str) -> Option<usize> {
+    let mut depth = 0;
+    let mut pos = 0;
+    while pos < s.len() {
+        let rest = &s[pos..];
+        if rest.starts_with("/*") {
+            depth += 1;
+            pos += 2;
+        } else if rest.starts_with("*/") {
+            depth -= 1;
+            pos += 2;
+            if depth == 0 {
+                return Some(pos);
+            }
+        } else {
+            pos += rest.chars().next().unwrap().len_utf8();
+        }
+    }
+    None
 }
 
 fn parse_char_literal(i: &mut Lexer<Token>) -> Option<char> {
-    unescape::unescape(&i.slice()[1..i.slice().len() - 1]).and_then(|x| x.chars().next())
+    let res = match unescape(&i.slice()[1..i.slice().len() - 1]) {
+        Some(s) => {
+            let mut chars = s.chars();
+            match (chars.next(), chars.next()) {
+                (Some(c), None) => return Some(c),
+                _ => LexErrorKind::InvalidCharLiteral,
+            }
+        }
+        None => LexErrorKind::BadEscape,
+    };
+    i.extras = Some(res);
+    None
 }
 
 #[derive(Debug, Clone, Logos)]
-#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[logos(extras = Option<LexErrorKind>)]
+#[cfg_attr(feature = "serde_impl", derive(serde::Serialize, serde::Deserialize))]
 pub enum Token {
     #[token("fn")]
     FnKw,
+    #[token("extern")]
+    ExternKw,
+    #[token("import")]
+    ImportKw,
     #[token("let")]
     LetKw,
     #[token("const")]
@@ -25,6 +144,8 @@ pub enum Token {
     AsKw,
     #[token("while")]
     WhileKw,
+    #[token("for")]
+    ForKw,
     #[token("if")]
     IfKw,
     #[token("else")]
@@ -35,14 +156,22 @@ pub enum Token {
     BreakKw,
     #[token("continue")]
     ContinueKw,
+    #[token("true")]
+    TrueKw,
+    #[token("false")]
+    FalseKw,
 
-    #[regex(r"\d+", |lex| lex.slice().parse())]
+    #[regex(r"\d[\d_]*", parse_int_literal)]
+    #[regex(r"0[xX][0-9a-fA-F_]+", parse_int_literal)]
+    #[regex(r"0[oO][0-7_]+", parse_int_literal)]
+    #[regex(r"0[bB][01_]+", parse_int_literal)]
     UIntLiteral(u64),
-    #[regex(r"\d+\.\d+([eE][+-]?\d+)?", |lex| lex.slice().parse())]
+    #[regex(r"\d[\d_]*\.\d[\d_]*([eE][+-]?\d[\d_]*)?", parse_float_literal)]
     FloatLiteral(f64),
-    #[regex(r#"'([^\\']|\\[rnt\\/"'])'"#, parse_char_literal)]
+    #[regex(r"'([^\\'\n]|\\.)*'", parse_char_literal)]
     CharLiteral(char),
-    #[regex(r#""([^\\"]|\\([rnt\\/"']))*""#, parse_string_literal)]
+    #[regex(r#""([^\\"]|\\.)*""#, parse_string_literal)]
+    #[regex(r#""([^\\"]|\\.)*"#, unterminated_string)]
     StringLiteral(String),
     #[regex(r"[_a-zA-Z][_a-zA-Z0-9]*", |lex| SmolStr::new(lex.slice()))]
     Ident(SmolStr),
@@ -55,8 +184,36 @@ pub enum Token {
     Mul,
     #[token(r"/")]
     Div,
+    #[token(r"%")]
+    Mod,
+    #[token(r"&")]
+    BitAnd,
+    #[token(r"|")]
+    BitOr,
+    #[token(r"^")]
+    Xor,
+    #[token(r"~")]
+    BitNot,
+    #[token(r"<<")]
+    Shl,
+    #[token(r">>")]
+    Shr,
     #[token(r"=")]
     Assign,
+    #[token(r"+=")]
+    AddAssign,
+    #[token(r"-=")]
+    SubAssign,
+    #[token(r"*=")]
+    MulAssign,
+    #[token(r"/=")]
+    DivAssign,
+    #[token(r"%=")]
+    ModAssign,
+    #[token(r"++")]
+    Inc,
+    #[token(r"--")]
+    Dec,
     #[token(r"==")]
     Eq,
     #[token(r"!=")]
@@ -69,10 +226,20 @@ pub enum Token {
     Le,
     #[token(r">=")]
     Ge,
+    #[token(r"&&")]
+    And,
+    #[token(r"||")]
+    Or,
+    #[token(r"!")]
+    Not,
     #[token("(")]
     LParen,
     #[token(")")]
     RParen,
+    #[token("[")]
+    LBracket,
+    #[token("]")]
+    RBracket,
     #[token("{")]
     LBrace,
     #[token("}")]
@@ -89,9 +256,15 @@ pub enum Token {
     // Empty stuff
     #[regex(r"\s+", logos::skip, priority = 1)]
     Whitespace,
-    #[regex(r"//.*\n", logos::skip)]
+    #[regex(r"//[^\n]*", logos::skip)]
     Comment,
 
+    /// A malformed piece of source code that the lexer can tell the reason
+    /// of. Block comments are lexed here too, but only unterminated ones are
+    /// emitted.
+    #[token("/*", skip_block_comment)]
+    LexError(LexErrorKind),
+
     // Error token
     #[error]
     Error,
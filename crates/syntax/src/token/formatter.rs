@@ -19,15 +19,20 @@ impl Display for Token {
         write_match! {
             self, f,
             Token::FnKw => {"fn"}
+            Token::ExternKw => {"extern"}
+            Token::ImportKw => {"import"}
             Token::LetKw => {"let"}
             Token::ConstKw => {"const"}
             Token::AsKw => {"as"}
             Token::WhileKw => {"while"}
+            Token::ForKw => {"for"}
             Token::IfKw => {"if"}
             Token::ElseKw => {"else"}
             Token::ReturnKw => {"return"}
             Token::BreakKw => {"break"}
             Token::ContinueKw => {"continue"}
+            Token::TrueKw => {"true"}
+            Token::FalseKw => {"false"}
             Token::UIntLiteral(i) => {"uint {}",i}
             Token::FloatLiteral(i) => {"float {}", i}
             Token::CharLiteral(c) => {"char {}", c}
@@ -37,15 +42,34 @@ impl Display for Token {
             Token::Minus => {"minus"}
             Token::Mul => {"mul"}
             Token::Div => {"div"}
+            Token::Mod => {"mod"}
+            Token::BitAnd => {"bitand"}
+            Token::BitOr => {"bitor"}
+            Token::Xor => {"xor"}
+            Token::BitNot => {"bitnot"}
+            Token::Shl => {"shl"}
+            Token::Shr => {"shr"}
             Token::Assign => {"assign"}
+            Token::AddAssign => {"addassign"}
+            Token::SubAssign => {"subassign"}
+            Token::MulAssign => {"mulassign"}
+            Token::DivAssign => {"divassign"}
+            Token::ModAssign => {"modassign"}
+            Token::Inc => {"inc"}
+            Token::Dec => {"dec"}
             Token::Eq => {"eq"}
             Token::Neq => {"neq"}
             Token::Lt => {"lt"}
             Token::Gt => {"gt"}
             Token::Le => {"le"}
             Token::Ge => {"ge"}
+            Token::And => {"and"}
+            Token::Or => {"or"}
+            Token::Not => {"not"}
             Token::LParen => {"lparen"}
             Token::RParen => {"rparen"}
+            Token::LBracket => {"lbracket"}
+            Token::RBracket => {"rbracket"}
             Token::LBrace => {"lbrace"}
             Token::RBrace => {"rbrace"}
             Token::Arrow => {"arrow"}
@@ -54,6 +78,7 @@ impl Display for Token {
             Token::Semicolon => {"semicolon"}
             Token::Whitespace => {"WS"}
             Token::Comment => {"comment"}
+            Token::LexError(kind) => {"err {}", kind}
             Token::Error => {"err"}
         }
     }
@@ -1,4 +1,4 @@
-use crate::{ast::*, span::Span};
+use crate::{ast::*, span::Span, util::P};
 
 macro_rules! walk {
     ($name:ident ($v:ident , $tgt:ident : $ty:ty) $(-> $r:ty )? $body:block) => {
@@ -10,8 +10,11 @@ macro_rules! walk {
 
 /// A handy trait for traversing the abstract syntax tree.
 ///
-/// Every function inside this visitor should call the corresponding
-/// `walk_<type>` function in order to traverse deeper into the tree.
+/// By default every method walks through all children of the node, discards
+/// their results and returns the default result of its kind, e.g.
+/// [`default_expr_result`](AstVisitor::default_expr_result) for expressions.
+/// Overriding methods may call the corresponding `walk_<type>` function in
+/// order to traverse deeper into the tree.
 pub trait AstVisitor {
     type LExprResult;
     type ExprResult;
@@ -20,30 +23,64 @@ pub trait AstVisitor {
     type ProgramResult;
     type FuncResult;
 
+    /// The result of default methods visiting left-hand side expressions.
+    fn default_lexpr_result() -> Self::LExprResult;
+
+    /// The result of default methods visiting expressions.
+    fn default_expr_result() -> Self::ExprResult;
+
+    /// The result of default methods visiting types.
+    fn default_ty_result() -> Self::TyResult;
+
+    /// The result of default methods visiting statements.
+    fn default_stmt_result() -> Self::StmtResult;
+
+    /// The result of default methods visiting programs.
+    fn default_program_result() -> Self::ProgramResult;
+
+    /// The result of default methods visiting functions.
+    fn default_func_result() -> Self::FuncResult;
+
     fn visit_program(&mut self, program: &Program) -> Self::ProgramResult {
         for decl in &program.decls {
             self.visit_decl_stmt(decl);
         }
+        for func in &program.externs {
+            self.visit_extern_func(func);
+        }
         for func in &program.funcs {
             self.visit_func(func);
         }
-        todo!("Visit program")
+        Self::default_program_result()
+    }
+
+    fn visit_extern_func(&mut self, func: &ExternFuncStmt) -> Self::FuncResult {
+        for param in &func.params {
+            self.visit_func_param(param);
+        }
+        self.visit_ty(&func.ret_ty);
+        Self::default_func_result()
     }
 
     fn visit_func(&mut self, func: &FuncStmt) -> Self::FuncResult {
         for param in &func.params {
             self.visit_func_param(param);
         }
+        self.visit_ty(&func.ret_ty);
         self.visit_block_stmt(&func.body);
-        todo!("Visit function")
+        Self::default_func_result()
     }
 
-    fn visit_func_param(&mut self, _param: &FuncParam) -> Self::StmtResult {
-        todo!("Visit function param")
+    fn visit_func_param(&mut self, param: &FuncParam) -> Self::StmtResult {
+        self.visit_ty(&param.ty);
+        Self::default_stmt_result()
     }
 
-    fn visit_ty(&mut self, _ty: &TyDef) -> Self::TyResult {
-        todo!("Visit type")
+    fn visit_ty(&mut self, ty: &TyDef) -> Self::TyResult {
+        if let TyDefKind::Array { elem, .. } = &ty.kind {
+            self.visit_ty(elem);
+        }
+        Self::default_ty_result()
     }
 
     fn visit_expr(&mut self, expr: &Expr) -> Self::ExprResult {
@@ -51,45 +88,57 @@ pub trait AstVisitor {
     }
 
     fn visit_literal_expr(&mut self, _expr: &LiteralExpr) -> Self::ExprResult {
-        todo!("Visit literal expr")
+        Self::default_expr_result()
     }
 
     fn visit_ident_expr(&mut self, _expr: &Ident) -> Self::ExprResult {
-        todo!("visit")
+        Self::default_expr_result()
     }
 
     fn visit_assign_expr(&mut self, expr: &AssignExpr) -> Self::ExprResult {
         self.visit_lexpr(&expr.lhs);
         self.visit_expr(&expr.rhs);
-        todo!("visit")
+        Self::default_expr_result()
     }
 
-    fn visit_lexpr(&mut self, _expr: &Expr) -> Self::LExprResult {
-        todo!("visit")
+    fn visit_lexpr(&mut self, expr: &Expr) -> Self::LExprResult {
+        self.visit_expr(expr);
+        Self::default_lexpr_result()
     }
 
     fn visit_binary_expr(&mut self, expr: &BinaryExpr) -> Self::ExprResult {
         self.visit_expr(&expr.lhs);
         self.visit_expr(&expr.rhs);
-        todo!("visit")
+        Self::default_expr_result()
     }
 
     fn visit_unary_expr(&mut self, expr: &UnaryExpr) -> Self::ExprResult {
         self.visit_expr(&expr.expr);
-        todo!("visit")
+        Self::default_expr_result()
+    }
+
+    fn visit_inc_dec_expr(&mut self, expr: &IncDecExpr) -> Self::ExprResult {
+        self.visit_lexpr(&expr.expr);
+        Self::default_expr_result()
     }
 
     fn visit_call_expr(&mut self, expr: &CallExpr) -> Self::ExprResult {
         for subexpr in &expr.params {
-            self.visit_expr(&subexpr);
+            self.visit_expr(subexpr);
         }
-        todo!("visit")
+        Self::default_expr_result()
+    }
+
+    fn visit_index_expr(&mut self, expr: &IndexExpr) -> Self::ExprResult {
+        self.visit_expr(&expr.base);
+        self.visit_expr(&expr.index);
+        Self::default_expr_result()
     }
 
     fn visit_as_expr(&mut self, expr: &AsExpr) -> Self::ExprResult {
         self.visit_ty(&expr.ty);
         self.visit_expr(&expr.val);
-        todo!("visit")
+        Self::default_expr_result()
     }
 
     fn visit_stmt(&mut self, stmt: &Stmt) -> Self::StmtResult {
@@ -100,13 +149,27 @@ pub trait AstVisitor {
         for substmt in &stmt.stmts {
             self.visit_stmt(substmt);
         }
-        todo!("visit")
+        Self::default_stmt_result()
     }
 
     fn visit_while_stmt(&mut self, stmt: &WhileStmt) -> Self::StmtResult {
         self.visit_expr(&stmt.cond);
         self.visit_block_stmt(&stmt.body);
-        todo!("visit")
+        Self::default_stmt_result()
+    }
+
+    fn visit_for_stmt(&mut self, stmt: &ForStmt) -> Self::StmtResult {
+        if let Some(init) = &stmt.init {
+            self.visit_stmt(init);
+        }
+        if let Some(cond) = &stmt.cond {
+            self.visit_expr(cond);
+        }
+        if let Some(step) = &stmt.step {
+            self.visit_expr(step);
+        }
+        self.visit_block_stmt(&stmt.body);
+        Self::default_stmt_result()
     }
 
     fn visit_if_stmt(&mut self, stmt: &IfStmt) -> Self::StmtResult {
@@ -115,18 +178,18 @@ pub trait AstVisitor {
         match &stmt.else_block {
             IfElseBlock::None => {}
             IfElseBlock::If(stmt) => {
-                self.visit_if_stmt(&stmt);
+                self.visit_if_stmt(stmt);
             }
             IfElseBlock::Block(blk) => {
-                self.visit_block_stmt(&blk);
+                self.visit_block_stmt(blk);
             }
         }
-        todo!("visit")
+        Self::default_stmt_result()
     }
 
     fn visit_expr_stmt(&mut self, stmt: &Expr) -> Self::StmtResult {
         self.visit_expr(stmt);
-        todo!("visit")
+        Self::default_stmt_result()
     }
 
     fn visit_decl_stmt(&mut self, stmt: &DeclStmt) -> Self::StmtResult {
@@ -134,26 +197,30 @@ pub trait AstVisitor {
         if let Some(expr) = &stmt.val {
             self.visit_expr(expr);
         }
-        todo!("visit")
+        Self::default_stmt_result()
     }
 
     fn visit_return_stmt(&mut self, stmt: &ReturnStmt) -> Self::StmtResult {
         if let Some(res) = &stmt.val {
-            self.visit_expr(&res);
+            self.visit_expr(res);
         }
-        todo!("visit")
+        Self::default_stmt_result()
     }
 
     fn visit_break_stmt(&mut self, _span: Span) -> Self::StmtResult {
-        todo!("visit")
+        Self::default_stmt_result()
     }
 
     fn visit_continue_stmt(&mut self, _span: Span) -> Self::StmtResult {
-        todo!("visit")
+        Self::default_stmt_result()
     }
 
     fn visit_empty_stmt(&mut self, _span: Span) -> Self::StmtResult {
-        todo!("visit")
+        Self::default_stmt_result()
+    }
+
+    fn visit_error_stmt(&mut self, _span: Span) -> Self::StmtResult {
+        Self::default_stmt_result()
     }
 }
 
@@ -161,6 +228,7 @@ walk! { walk_stmt(v, stmt: Stmt) -> V::StmtResult {
     match stmt {
         Stmt::Block(b) => v.visit_block_stmt(b),
         Stmt::While(s) => {v.visit_while_stmt(s)}
+        Stmt::For(s) => {v.visit_for_stmt(s)}
         Stmt::If(s) => {v.visit_if_stmt(s)}
         Stmt::Expr(s) => {v.visit_expr_stmt(s)}
         Stmt::Decl(s) => {v.visit_decl_stmt(s)}
@@ -168,6 +236,7 @@ walk! { walk_stmt(v, stmt: Stmt) -> V::StmtResult {
         Stmt::Break(s) => {v.visit_break_stmt(*s)}
         Stmt::Continue(s) => {v.visit_continue_stmt(*s)}
         Stmt::Empty(s) => {v.visit_empty_stmt(*s)}
+        Stmt::Error(s) => {v.visit_error_stmt(*s)}
     }
 }}
 
@@ -180,5 +249,198 @@ walk! { walk_expr(v, expr: Expr) -> V::ExprResult{
         Expr::Ident(x)=>{v.visit_ident_expr(x)}
         Expr::Literal(x)=>{v.visit_literal_expr(x)}
         Expr::Unary(x)=>{v.visit_unary_expr(x)}
+        Expr::IncDec(x)=>{v.visit_inc_dec_expr(x)}
+        Expr::Index(x)=>{v.visit_index_expr(x)}
+    }
+}}
+
+macro_rules! walk_mut {
+    ($name:ident ($v:ident , $tgt:ident : $ty:ty) $body:block) => {
+        pub fn $name<V: AstVisitorMut + ?Sized>($v: &mut V, $tgt: &mut $ty) $body
+    };
+}
+
+/// A mutable counterpart of [`AstVisitor`], used for rewriting the abstract
+/// syntax tree in place.
+///
+/// Nodes behind a [`P`](crate::util::P) are cloned if they are shared
+/// elsewhere before being visited. Every method walks through all children of
+/// the node by default; overriding methods may replace the node entirely, or
+/// call the corresponding `walk_<type>_mut` function to keep traversing.
+pub trait AstVisitorMut {
+    fn visit_program(&mut self, program: &mut Program) {
+        for decl in &mut program.decls {
+            self.visit_decl_stmt(decl);
+        }
+        for func in &mut program.externs {
+            self.visit_extern_func(func);
+        }
+        for func in &mut program.funcs {
+            self.visit_func(func);
+        }
+    }
+
+    fn visit_extern_func(&mut self, func: &mut ExternFuncStmt) {
+        for param in &mut func.params {
+            self.visit_func_param(param);
+        }
+        self.visit_ty(&mut func.ret_ty);
+    }
+
+    fn visit_func(&mut self, func: &mut FuncStmt) {
+        for param in &mut func.params {
+            self.visit_func_param(param);
+        }
+        self.visit_ty(&mut func.ret_ty);
+        self.visit_block_stmt(&mut func.body);
+    }
+
+    fn visit_func_param(&mut self, param: &mut FuncParam) {
+        self.visit_ty(&mut param.ty);
+    }
+
+    fn visit_ty(&mut self, ty: &mut TyDef) {
+        if let TyDefKind::Array { elem, .. } = &mut ty.kind {
+            self.visit_ty(P::make_mut(elem));
+        }
+    }
+
+    fn visit_expr(&mut self, expr: &mut Expr) {
+        walk_expr_mut(self, expr)
+    }
+
+    fn visit_literal_expr(&mut self, _expr: &mut LiteralExpr) {}
+
+    fn visit_ident_expr(&mut self, _expr: &mut Ident) {}
+
+    fn visit_assign_expr(&mut self, expr: &mut AssignExpr) {
+        self.visit_lexpr(P::make_mut(&mut expr.lhs));
+        self.visit_expr(P::make_mut(&mut expr.rhs));
+    }
+
+    fn visit_lexpr(&mut self, expr: &mut Expr) {
+        self.visit_expr(expr);
+    }
+
+    fn visit_binary_expr(&mut self, expr: &mut BinaryExpr) {
+        self.visit_expr(P::make_mut(&mut expr.lhs));
+        self.visit_expr(P::make_mut(&mut expr.rhs));
+    }
+
+    fn visit_unary_expr(&mut self, expr: &mut UnaryExpr) {
+        self.visit_expr(P::make_mut(&mut expr.expr));
+    }
+
+    fn visit_inc_dec_expr(&mut self, expr: &mut IncDecExpr) {
+        self.visit_lexpr(P::make_mut(&mut expr.expr));
+    }
+
+    fn visit_call_expr(&mut self, expr: &mut CallExpr) {
+        for subexpr in &mut expr.params {
+            self.visit_expr(subexpr);
+        }
+    }
+
+    fn visit_index_expr(&mut self, expr: &mut IndexExpr) {
+        self.visit_expr(P::make_mut(&mut expr.base));
+        self.visit_expr(P::make_mut(&mut expr.index));
+    }
+
+    fn visit_as_expr(&mut self, expr: &mut AsExpr) {
+        self.visit_ty(&mut expr.ty);
+        self.visit_expr(P::make_mut(&mut expr.val));
+    }
+
+    fn visit_stmt(&mut self, stmt: &mut Stmt) {
+        walk_stmt_mut(self, stmt)
+    }
+
+    fn visit_block_stmt(&mut self, stmt: &mut BlockStmt) {
+        for substmt in &mut stmt.stmts {
+            self.visit_stmt(substmt);
+        }
+    }
+
+    fn visit_while_stmt(&mut self, stmt: &mut WhileStmt) {
+        self.visit_expr(P::make_mut(&mut stmt.cond));
+        self.visit_block_stmt(P::make_mut(&mut stmt.body));
+    }
+
+    fn visit_for_stmt(&mut self, stmt: &mut ForStmt) {
+        if let Some(init) = &mut stmt.init {
+            self.visit_stmt(P::make_mut(init));
+        }
+        if let Some(cond) = &mut stmt.cond {
+            self.visit_expr(P::make_mut(cond));
+        }
+        if let Some(step) = &mut stmt.step {
+            self.visit_expr(P::make_mut(step));
+        }
+        self.visit_block_stmt(P::make_mut(&mut stmt.body));
+    }
+
+    fn visit_if_stmt(&mut self, stmt: &mut IfStmt) {
+        self.visit_expr(P::make_mut(&mut stmt.cond));
+        self.visit_block_stmt(P::make_mut(&mut stmt.if_block));
+        match &mut stmt.else_block {
+            IfElseBlock::None => {}
+            IfElseBlock::If(stmt) => self.visit_if_stmt(P::make_mut(stmt)),
+            IfElseBlock::Block(blk) => self.visit_block_stmt(P::make_mut(blk)),
+        }
+    }
+
+    fn visit_expr_stmt(&mut self, stmt: &mut Expr) {
+        self.visit_expr(stmt);
+    }
+
+    fn visit_decl_stmt(&mut self, stmt: &mut DeclStmt) {
+        self.visit_ty(&mut stmt.ty);
+        if let Some(expr) = &mut stmt.val {
+            self.visit_expr(P::make_mut(expr));
+        }
+    }
+
+    fn visit_return_stmt(&mut self, stmt: &mut ReturnStmt) {
+        if let Some(res) = &mut stmt.val {
+            self.visit_expr(P::make_mut(res));
+        }
+    }
+
+    fn visit_break_stmt(&mut self, _span: Span) {}
+
+    fn visit_continue_stmt(&mut self, _span: Span) {}
+
+    fn visit_empty_stmt(&mut self, _span: Span) {}
+
+    fn visit_error_stmt(&mut self, _span: Span) {}
+}
+
+walk_mut! { walk_stmt_mut(v, stmt: Stmt) {
+    match stmt {
+        Stmt::Block(b) => v.visit_block_stmt(b),
+        Stmt::While(s) => v.visit_while_stmt(s),
+        Stmt::For(s) => v.visit_for_stmt(s),
+        Stmt::If(s) => v.visit_if_stmt(s),
+        Stmt::Expr(s) => v.visit_expr_stmt(s),
+        Stmt::Decl(s) => v.visit_decl_stmt(s),
+        Stmt::Return(s) => v.visit_return_stmt(s),
+        Stmt::Break(s) => v.visit_break_stmt(*s),
+        Stmt::Continue(s) => v.visit_continue_stmt(*s),
+        Stmt::Empty(s) => v.visit_empty_stmt(*s),
+        Stmt::Error(s) => v.visit_error_stmt(*s),
+    }
+}}
+
+walk_mut! { walk_expr_mut(v, expr: Expr) {
+    match expr {
+        Expr::As(x) => v.visit_as_expr(x),
+        Expr::Binary(b) => v.visit_binary_expr(b),
+        Expr::Assign(x) => v.visit_assign_expr(x),
+        Expr::Call(x) => v.visit_call_expr(x),
+        Expr::Ident(x) => v.visit_ident_expr(x),
+        Expr::Literal(x) => v.visit_literal_expr(x),
+        Expr::Unary(x) => v.visit_unary_expr(x),
+        Expr::IncDec(x) => v.visit_inc_dec_expr(x),
+        Expr::Index(x) => v.visit_index_expr(x),
     }
 }}
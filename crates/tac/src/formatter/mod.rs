@@ -41,6 +41,9 @@ impl Display for Ty {
             Ty::Ptr(tgt) => {
                 write!(f, "{}*", tgt)
             }
+            Ty::Array(arr) => {
+                write!(f, "[{}; {}]", arr.elem, arr.len)
+            }
             Ty::Numeric(ty) => ty.fmt(f),
         }
     }
@@ -68,6 +71,9 @@ impl Display for NumericTy {
             TyKind::Int => {
                 write!(f, "i")
             }
+            TyKind::Float => {
+                write!(f, "f")
+            }
         }?;
         write!(f, "{}", self.size)
     }
@@ -80,6 +86,12 @@ impl Display for BinaryOp {
             BinaryOp::Sub => "sub",
             BinaryOp::Mul => "mul",
             BinaryOp::Div => "div",
+            BinaryOp::Mod => "mod",
+            BinaryOp::And => "and",
+            BinaryOp::Or => "or",
+            BinaryOp::Xor => "xor",
+            BinaryOp::Shl => "shl",
+            BinaryOp::Shr => "shr",
             BinaryOp::Lt => "lt",
             BinaryOp::Gt => "gt",
             BinaryOp::Le => "le",
@@ -102,6 +114,26 @@ impl Display for VarId {
     }
 }
 
+/// A float immediate in the form accepted by the parser. Finite values always
+/// have a fractional part, e.g. `1.0` or `1.0e20`.
+struct FloatLit(f64);
+impl Display for FloatLit {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let x = self.0;
+        if x.is_nan() {
+            return write!(f, "nan");
+        } else if x.is_infinite() {
+            return write!(f, "{}inf", if x < 0.0 { "-" } else { "" });
+        }
+        let s = format!("{:?}", x);
+        match s.find(['.', 'e']) {
+            Some(idx) if s.as_bytes()[idx] == b'e' => write!(f, "{}.0{}", &s[..idx], &s[idx..]),
+            Some(_) => write!(f, "{}", s),
+            None => write!(f, "{}.0", s),
+        }
+    }
+}
+
 impl FormatContext<&mut TacFormatCtx> for Value {
     fn fmt_ctx(&self, f: &mut std::fmt::Formatter<'_>, ctx: &mut TacFormatCtx) -> std::fmt::Result {
         match self {
@@ -111,6 +143,7 @@ impl FormatContext<&mut TacFormatCtx> for Value {
             Value::Imm(imm) => {
                 write!(f, "#{}", imm)
             }
+            Value::FloatImm(imm) => write!(f, "#{}", FloatLit(imm.get())),
         }
     }
 }
@@ -140,6 +173,16 @@ impl FormatContext<(VarId, &mut TacFormatCtx)> for Tac {
                 }
                 write!(f, ")")?;
             }
+            InstKind::Intrinsic(call) => {
+                write!(f, "intrinsic {} (", call.intrinsic.name())?;
+                for (idx, param) in call.params.iter().enumerate() {
+                    if idx != 0 {
+                        write!(f, ", ")?;
+                    }
+                    param.fmt_ctx(f, ctx.1)?;
+                }
+                write!(f, ")")?;
+            }
 
             InstKind::Assign(i) => {
                 i.fmt_ctx(f, ctx.1)?;
@@ -147,6 +190,43 @@ impl FormatContext<(VarId, &mut TacFormatCtx)> for Tac {
             InstKind::Param(id) => {
                 write!(f, "param {}", id)?;
             }
+            InstKind::LoadGlobal(name) => {
+                write!(f, "load @{}", name)?;
+            }
+            InstKind::StoreGlobal(name, val) => {
+                write!(f, "store @{} ", name)?;
+                val.fmt_ctx(f, ctx.1)?;
+            }
+            InstKind::Addr(name) => {
+                write!(f, "addr @{}", name)?;
+            }
+            InstKind::Alloca(count) => {
+                write!(f, "alloca {}", count)?;
+            }
+            InstKind::Load(ptr) => {
+                write!(f, "load ")?;
+                ptr.fmt_ctx(f, ctx.1)?;
+            }
+            InstKind::Store { ptr, val } => {
+                write!(f, "store ")?;
+                ptr.fmt_ctx(f, ctx.1)?;
+                write!(f, " ")?;
+                val.fmt_ctx(f, ctx.1)?;
+            }
+            InstKind::PtrOffset { ptr, offset } => {
+                write!(f, "offset ")?;
+                ptr.fmt_ctx(f, ctx.1)?;
+                write!(f, " ")?;
+                offset.fmt_ctx(f, ctx.1)?;
+            }
+            InstKind::IntToFloat(v) => {
+                write!(f, "itof ")?;
+                v.fmt_ctx(f, ctx.1)?;
+            }
+            InstKind::FloatToInt(v) => {
+                write!(f, "ftoi ")?;
+                v.fmt_ctx(f, ctx.1)?;
+            }
             InstKind::Phi(phi) => {
                 write!(f, "phi [")?;
                 let mut first = true;
@@ -189,6 +269,47 @@ impl FormatContext<&mut TacFormatCtx> for Branch {
     }
 }
 
+impl Display for GlobalVar {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} #{}", self.ty, self.init)
+    }
+}
+
+impl Display for Program {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for (name, global) in &self.globals {
+            writeln!(f, "global @{} = {}", name, global)?;
+        }
+        for (name, data) in &self.data {
+            // `{:?}` escapes the string in the same way the parser reads it
+            writeln!(f, "data @{} = {:?}", name, data)?;
+        }
+        for (name, ty) in &self.externs {
+            let ty = ty.as_func().unwrap();
+            let param_fmt = ListFormatter::new(ty.params.iter());
+            writeln!(
+                f,
+                "extern fn @{}({}) -> {}",
+                name, param_fmt, &ty.return_type
+            )?;
+        }
+        if !self.globals.is_empty() || !self.data.is_empty() || !self.externs.is_empty() {
+            writeln!(f)?;
+        }
+
+        // Sort functions by name so the output is stable between runs
+        let mut functions = self.functions.values().collect::<Vec<_>>();
+        functions.sort_by(|a, b| a.name.cmp(&b.name));
+        for (idx, func) in functions.into_iter().enumerate() {
+            if idx != 0 {
+                writeln!(f)?;
+            }
+            write!(f, "{}", func)?;
+        }
+        Ok(())
+    }
+}
+
 impl std::fmt::Display for TacFunc {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         let ty = self.ty.as_func().unwrap();
@@ -20,6 +20,8 @@ pub mod parser;
 pub mod ty;
 pub mod util;
 
+mod test;
+
 use std::collections::{BTreeMap, HashMap};
 
 use enum_as_inner::EnumAsInner;
@@ -29,7 +31,7 @@ use linkedlist::{ImplicitLinkedList, ImplicitLinkedListItem};
 use smol_str::SmolStr;
 use thunderdome::{Arena, Index};
 
-pub use ty::{NumericTy, Ty, TyKind};
+pub use ty::{ArrayTy, NumericTy, Ty, TyKind};
 use util::VarIter;
 
 pub use containers::{BBId, InstId};
@@ -37,6 +39,23 @@ pub use containers::{BBId, InstId};
 #[derive(Debug, Clone)]
 pub struct Program {
     pub functions: HashMap<SmolStr, TacFunc>,
+    /// Functions provided by the host running the program, mapped to their
+    /// types. They are called using [`InstKind::FunctionCall`] like any other
+    /// function.
+    pub externs: BTreeMap<SmolStr, Ty>,
+    pub globals: BTreeMap<SmolStr, GlobalVar>,
+    /// Read-only data, e.g. string literals. Addresses of data are taken using
+    /// [`InstKind::Addr`].
+    pub data: BTreeMap<SmolStr, String>,
+}
+
+/// A global variable. Global variables live throughout the whole program, and
+/// are accessed using [`InstKind::LoadGlobal`] and [`InstKind::StoreGlobal`].
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct GlobalVar {
+    pub ty: Ty,
+    /// The initial value of this variable
+    pub init: Immediate,
 }
 
 /// A function made of TAC instructions.
@@ -449,6 +468,57 @@ pub struct FunctionCall {
     pub params: Vec<Value>,
 }
 
+/// A call to a function provided by the runtime, e.g. I/O functions.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct IntrinsicCall {
+    pub intrinsic: Intrinsic,
+    pub params: Vec<Value>,
+}
+
+/// Functions provided by the runtime.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub enum Intrinsic {
+    /// `getint() -> int`: Reads an integer from input, skipping whitespaces
+    GetInt,
+    /// `getchar() -> int`: Reads a character from input, or returns -1 at the
+    /// end of input
+    GetChar,
+    /// `putint(int) -> void`: Prints an integer
+    PutInt,
+    /// `putchar(int) -> void`: Prints a character
+    PutChar,
+    /// `putstr(int*) -> void`: Prints a zero-terminated string
+    PutStr,
+    /// `putln() -> void`: Prints a line break
+    PutLn,
+}
+
+impl Intrinsic {
+    pub const ALL: [Intrinsic; 6] = [
+        Intrinsic::GetInt,
+        Intrinsic::GetChar,
+        Intrinsic::PutInt,
+        Intrinsic::PutChar,
+        Intrinsic::PutStr,
+        Intrinsic::PutLn,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Intrinsic::GetInt => "getint",
+            Intrinsic::GetChar => "getchar",
+            Intrinsic::PutInt => "putint",
+            Intrinsic::PutChar => "putchar",
+            Intrinsic::PutStr => "putstr",
+            Intrinsic::PutLn => "putln",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Intrinsic> {
+        Self::ALL.iter().copied().find(|i| i.name() == name)
+    }
+}
+
 #[derive(Debug, Clone, Eq, PartialEq)]
 pub struct Inst {
     pub kind: InstKind,
@@ -462,6 +532,8 @@ pub enum InstKind {
     Binary(BinaryInst),
     /// A call to another function.
     FunctionCall(FunctionCall),
+    /// A call to a function provided by the runtime.
+    Intrinsic(IntrinsicCall),
 
     /// An assignment from another instruction or constant
     Assign(Value),
@@ -469,6 +541,28 @@ pub enum InstKind {
     Phi(BTreeMap<BBId, InstId>),
     /// A function parameter
     Param(usize),
+    /// Reads the value of a global variable
+    LoadGlobal(SmolStr),
+    /// Writes the value into a global variable. Has a type of `unit`.
+    StoreGlobal(SmolStr, Value),
+    /// Gets the address of the given read-only data. Strings are stored with
+    /// one character per element, followed by a zero.
+    Addr(SmolStr),
+    /// Allocates space for the given number of elements in the current stack
+    /// frame, and returns a pointer to the first element. The type of this
+    /// instruction is the pointer type.
+    Alloca(usize),
+    /// Reads the value that the pointer points to
+    Load(Value),
+    /// Writes the value into the place that the pointer points to. Has a type
+    /// of `unit`.
+    Store { ptr: Value, val: Value },
+    /// Offsets the pointer by the given number of elements
+    PtrOffset { ptr: Value, offset: Value },
+    /// Converts an integer into a floating-point number
+    IntToFloat(Value),
+    /// Converts a floating-point number into an integer, rounding towards zero
+    FloatToInt(Value),
     /// An unreachable value
     Dead,
 }
@@ -480,12 +574,24 @@ impl InstKind {
             InstKind::FunctionCall(f) => {
                 VarIter::Iter(Box::new(f.params.iter().cloned()) as Box<dyn Iterator<Item = _>>)
             }
+            InstKind::Intrinsic(f) => {
+                VarIter::Iter(Box::new(f.params.iter().cloned()) as Box<dyn Iterator<Item = _>>)
+            }
             InstKind::Assign(v) => VarIter::One(*v),
             InstKind::Phi(source) => {
                 VarIter::Iter(Box::new(source.iter().map(|(_, &val)| val.into()))
                     as Box<dyn Iterator<Item = _>>)
             }
             InstKind::Param(_) => VarIter::None,
+            InstKind::LoadGlobal(_) => VarIter::None,
+            InstKind::Addr(_) => VarIter::None,
+            InstKind::StoreGlobal(_, v) => VarIter::One(*v),
+            InstKind::Alloca(_) => VarIter::None,
+            InstKind::Load(ptr) => VarIter::One(*ptr),
+            InstKind::Store { ptr, val } => VarIter::Two(*ptr, *val),
+            InstKind::PtrOffset { ptr, offset } => VarIter::Two(*ptr, *offset),
+            InstKind::IntToFloat(v) => VarIter::One(*v),
+            InstKind::FloatToInt(v) => VarIter::One(*v),
             InstKind::Dead => VarIter::None,
         }
     }
@@ -534,6 +640,12 @@ pub enum BinaryOp {
     Sub,
     Mul,
     Div,
+    Mod,
+    And,
+    Or,
+    Xor,
+    Shl,
+    Shr,
     Lt,
     Gt,
     Le,
@@ -546,13 +658,21 @@ pub enum BinaryOp {
 pub enum Value {
     Dest(InstId),
     Imm(Immediate),
+    FloatImm(FloatImm),
 }
 
 impl Value {
     pub fn get_imm(&self) -> Option<Immediate> {
         match self {
-            Value::Dest(_) => None,
             Value::Imm(i) => Some(*i),
+            _ => None,
+        }
+    }
+
+    pub fn get_float_imm(&self) -> Option<f64> {
+        match self {
+            Value::FloatImm(f) => Some(f.get()),
+            _ => None,
         }
     }
 
@@ -571,3 +691,24 @@ impl From<InstId> for Value {
 }
 
 type Immediate = i64;
+
+/// A floating-point immediate value. The value is stored as its bit pattern, so
+/// that it can be compared like other values.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub struct FloatImm(u64);
+
+impl FloatImm {
+    pub fn new(val: f64) -> FloatImm {
+        FloatImm(val.to_bits())
+    }
+
+    pub fn get(self) -> f64 {
+        f64::from_bits(self.0)
+    }
+}
+
+impl From<f64> for FloatImm {
+    fn from(val: f64) -> Self {
+        FloatImm::new(val)
+    }
+}
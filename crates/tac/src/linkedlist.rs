@@ -53,7 +53,7 @@ where
 
         let before_item = self.get_item_mut(before);
         let prev = before_item.prev();
-        before_item.set_next(Some(this));
+        before_item.set_prev(Some(this));
 
         let current = self.get_item_mut(this);
         current.set_next(Some(before));
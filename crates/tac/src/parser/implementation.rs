@@ -11,7 +11,7 @@ pub use combine::{EasyParser, Parser, Stream};
 use combine::{
     attempt, between, choice, eof,
     error::StreamError,
-    many, many1, one_of, optional, parser,
+    many, many1, none_of, one_of, optional, parser,
     parser::{
         char::{alpha_num, char, digit, hex_digit, newline, spaces as nl_spaces, string},
         combinator::ignore,
@@ -23,8 +23,8 @@ use smol_str::SmolStr;
 use std::{cell::RefCell, collections::BTreeMap, fmt::Display, ops::Neg};
 
 use crate::{
-    builder::FuncEditor, BBId, BinaryInst, BinaryOp, Branch, FunctionCall, Inst, InstId, InstKind,
-    NumericTy, Program, TacFunc, Ty, TyKind, Value,
+    builder::FuncEditor, BBId, BinaryInst, BinaryOp, Branch, FunctionCall, GlobalVar, Inst, InstId,
+    InstKind, Intrinsic, IntrinsicCall, NumericTy, Program, TacFunc, Ty, TyKind, Value,
 };
 
 struct VariableNamingCtx<'f> {
@@ -117,7 +117,7 @@ fn ident<Input>() -> impl Parser<Input, Output = String>
 where
     Input: Stream<Token = char>,
 {
-    (char('@'), many1(alpha_num())).map(|x| x.1)
+    (char('@'), many1(alpha_num().or(one_of("_$".chars())))).map(|x| x.1)
 }
 
 fn unsigned_dec_number<I, N>() -> impl Parser<I, Output = N>
@@ -159,12 +159,78 @@ where
     })
 }
 
+fn float_number<Input>() -> impl Parser<Input, Output = f64>
+where
+    Input: Stream<Token = char>,
+    Input::Error: ParseError<Input::Token, Input::Range, Input::Position>,
+{
+    (
+        optional(choice((char('-'), char('+')))),
+        many1(digit()),
+        char('.'),
+        many1(digit()),
+        optional((
+            one_of("eE".chars()),
+            optional(one_of("+-".chars())),
+            many1(digit()),
+        )),
+    )
+        .and_then(|(sign, int, _, frac, exp): (_, String, _, String, _)| {
+            let mut s = String::new();
+            if let Some(sign) = sign {
+                s.push(sign);
+            }
+            s.push_str(&int);
+            s.push('.');
+            s.push_str(&frac);
+            if let Some((e, exp_sign, exp)) = exp {
+                let exp: String = exp;
+                s.push(e);
+                if let Some(exp_sign) = exp_sign {
+                    s.push(exp_sign);
+                }
+                s.push_str(&exp);
+            }
+            s.parse::<f64>()
+                .map_err(StreamErrorFor::<Input>::message_format)
+        })
+}
+
+/// Matches `inf`, `-inf` or `nan`
+fn non_finite_float<Input>() -> impl Parser<Input, Output = f64>
+where
+    Input: Stream<Token = char>,
+    Input::Error: ParseError<Input::Token, Input::Range, Input::Position>,
+{
+    (
+        optional(choice((char('-'), char('+')))),
+        choice((
+            string("inf").map(|_| f64::INFINITY),
+            string("nan").map(|_| f64::NAN),
+        )),
+    )
+        .map(|(sign, x)| if sign == Some('-') { -x } else { x })
+}
+
+/// Matches a floating-point immediate, e.g. `#1.5`, `#1.0e20` or `#inf`
+fn float_imm<Input>() -> impl Parser<Input, Output = f64>
+where
+    Input: Stream<Token = char>,
+    Input::Error: ParseError<Input::Token, Input::Range, Input::Position>,
+{
+    (
+        char('#'),
+        choice((attempt(float_number()), non_finite_float())),
+    )
+        .map(|(_, num)| num)
+}
+
 fn number<Input>() -> impl Parser<Input, Output = i64>
 where
     Input: Stream<Token = char>,
     Input::Error: ParseError<Input::Token, Input::Range, Input::Position>,
 {
-    (char('#'), choice((hex_number(), dec_number()))).map(|(_, num)| num)
+    (char('#'), choice((attempt(hex_number()), dec_number()))).map(|(_, num)| num)
 }
 
 fn variable<Input>() -> impl Parser<Input, Output = usize>
@@ -183,6 +249,7 @@ where
     Input::Error: ParseError<Input::Token, Input::Range, Input::Position>,
 {
     choice((
+        attempt(float_imm().map(|f| Value::FloatImm(f.into()))),
         number().map(Value::Imm),
         variable().map(move |v| Value::Dest(ctx.borrow_mut().declared_var(v))),
     ))
@@ -233,6 +300,21 @@ where
     })
 }
 
+fn float_ty<Input>() -> impl Parser<Input, Output = Ty>
+where
+    Input: Stream<Token = char>,
+{
+    (char('f'), unsigned_dec_number::<_, u8>()).and_then(|(_, size)| {
+        if size != 64 {
+            return Err(StreamErrorFor::<Input>::message_format(format_args!(
+                "size {} of floating-point numbers must be 64",
+                size
+            )));
+        }
+        Ok(Ty::float())
+    })
+}
+
 fn unit_ty<Input>() -> impl Parser<Input, Output = Ty>
 where
     Input: Stream<Token = char>,
@@ -257,11 +339,38 @@ where
         .map(|(_, params, _, ret_ty)| Ty::func_of(ret_ty, params))
 }
 
+/// Matches an array type, e.g. `[i32; 3]`
+fn array_ty<Input>() -> impl Parser<Input, Output = Ty>
+where
+    Input: Stream<Token = char>,
+{
+    (
+        char('[').skip(spaces0()),
+        ty().skip(spaces0()),
+        char(';').skip(spaces0()),
+        unsigned_dec_number::<_, usize>().skip(spaces0()),
+        char(']'),
+    )
+        .map(|(_, elem, _, len, _)| Ty::array_of(elem, len))
+}
+
 fn _ty<Input>() -> impl Parser<Input, Output = Ty>
 where
     Input: Stream<Token = char>,
 {
-    choice((int_ty(), bool_ty(), unit_ty(), func_ty()))
+    // Pointer types are written as a postfix `*`, e.g. `i32*`
+    (
+        choice((
+            int_ty(),
+            bool_ty(),
+            float_ty(),
+            unit_ty(),
+            func_ty(),
+            array_ty(),
+        )),
+        many(char('*')),
+    )
+        .map(|(ty, stars): (_, String)| stars.chars().fold(ty, |ty, _| Ty::ptr_of(ty)))
 }
 
 parser! {
@@ -280,6 +389,12 @@ where
         attempt(string("sub")),
         attempt(string("mul")),
         attempt(string("div")),
+        attempt(string("mod")),
+        attempt(string("and")),
+        attempt(string("or")),
+        attempt(string("xor")),
+        attempt(string("shl")),
+        attempt(string("shr")),
         attempt(string("gt")),
         attempt(string("lt")),
         attempt(string("ge")),
@@ -292,6 +407,12 @@ where
         "sub" => BinaryOp::Sub,
         "mul" => BinaryOp::Mul,
         "div" => BinaryOp::Div,
+        "mod" => BinaryOp::Mod,
+        "and" => BinaryOp::And,
+        "or" => BinaryOp::Or,
+        "xor" => BinaryOp::Xor,
+        "shl" => BinaryOp::Shl,
+        "shr" => BinaryOp::Shr,
         "gt" => BinaryOp::Gt,
         "lt" => BinaryOp::Lt,
         "ge" => BinaryOp::Ge,
@@ -346,6 +467,33 @@ where
         })
 }
 
+fn intrinsic_instruction<'a, Input>(
+    ctx: &'a RefCell<VariableNamingCtx<'a>>,
+) -> impl Parser<Input, Output = IntrinsicCall> + 'a
+where
+    Input: Stream<Token = char> + 'a,
+{
+    (
+        string("intrinsic").skip(spaces1()),
+        many1(alpha_num())
+            .and_then(|name: String| {
+                Intrinsic::from_name(&name).ok_or_else(|| {
+                    StreamErrorFor::<Input>::message_format(format_args!(
+                        "unknown intrinsic {}",
+                        name
+                    ))
+                })
+            })
+            .skip(spaces0()),
+        between(
+            string("(").skip(spaces0()),
+            string(")"),
+            comma_sep_list(value(ctx)).skip(spaces0()),
+        ),
+    )
+        .map(|(_, intrinsic, params)| IntrinsicCall { intrinsic, params })
+}
+
 fn phi_instruction<'a, Input>(
     ctx: &'a RefCell<VariableNamingCtx<'a>>,
 ) -> impl Parser<Input, Output = BTreeMap<BBId, InstId>> + 'a
@@ -384,6 +532,95 @@ where
     (string("param").skip(spaces1()), unsigned_dec_number()).map(|(_, i)| i)
 }
 
+fn load_global_instruction<Input>() -> impl Parser<Input, Output = SmolStr>
+where
+    Input: Stream<Token = char>,
+{
+    (string("load").skip(spaces1()), ident()).map(|(_, name)| name.into())
+}
+
+fn store_global_instruction<'a, Input>(
+    ctx: &'a RefCell<VariableNamingCtx<'a>>,
+) -> impl Parser<Input, Output = (SmolStr, Value)> + 'a
+where
+    Input: Stream<Token = char> + 'a,
+{
+    (
+        string("store").skip(spaces1()),
+        ident().skip(spaces1()),
+        value(ctx),
+    )
+        .map(|(_, name, val)| (name.into(), val))
+}
+
+fn addr_instruction<Input>() -> impl Parser<Input, Output = SmolStr>
+where
+    Input: Stream<Token = char>,
+{
+    (string("addr").skip(spaces1()), ident()).map(|(_, name)| name.into())
+}
+
+fn alloca_instruction<Input>() -> impl Parser<Input, Output = usize>
+where
+    Input: Stream<Token = char>,
+{
+    (string("alloca").skip(spaces1()), unsigned_dec_number()).map(|(_, count)| count)
+}
+
+fn load_instruction<'a, Input>(
+    ctx: &'a RefCell<VariableNamingCtx<'a>>,
+) -> impl Parser<Input, Output = Value> + 'a
+where
+    Input: Stream<Token = char> + 'a,
+{
+    (string("load").skip(spaces1()), value(ctx)).map(|(_, ptr)| ptr)
+}
+
+fn store_instruction<'a, Input>(
+    ctx: &'a RefCell<VariableNamingCtx<'a>>,
+) -> impl Parser<Input, Output = (Value, Value)> + 'a
+where
+    Input: Stream<Token = char> + 'a,
+{
+    (
+        string("store").skip(spaces1()),
+        value(ctx).skip(spaces1()),
+        value(ctx),
+    )
+        .map(|(_, ptr, val)| (ptr, val))
+}
+
+fn offset_instruction<'a, Input>(
+    ctx: &'a RefCell<VariableNamingCtx<'a>>,
+) -> impl Parser<Input, Output = (Value, Value)> + 'a
+where
+    Input: Stream<Token = char> + 'a,
+{
+    (
+        string("offset").skip(spaces1()),
+        value(ctx).skip(spaces1()),
+        value(ctx),
+    )
+        .map(|(_, ptr, offset)| (ptr, offset))
+}
+
+fn conversion_instruction<'a, Input>(
+    ctx: &'a RefCell<VariableNamingCtx<'a>>,
+) -> impl Parser<Input, Output = InstKind> + 'a
+where
+    Input: Stream<Token = char> + 'a,
+{
+    (
+        choice((attempt(string("itof")), attempt(string("ftoi")))).skip(spaces1()),
+        value(ctx),
+    )
+        .map(|(op, val)| match op {
+            "itof" => InstKind::IntToFloat(val),
+            "ftoi" => InstKind::FloatToInt(val),
+            _ => unreachable!(),
+        })
+}
+
 fn instruction<'a, Input>(
     ctx: &'a RefCell<VariableNamingCtx<'a>>,
 ) -> impl Parser<Input, Output = ()> + 'a
@@ -398,7 +635,20 @@ where
             attempt(binary_instruction(ctx).map(InstKind::Binary)),
             attempt(value_instruction(ctx).map(InstKind::Assign)),
             attempt(func_call_instruction(ctx).map(InstKind::FunctionCall)),
+            attempt(intrinsic_instruction(ctx).map(InstKind::Intrinsic)),
             attempt(phi_instruction(ctx).map(InstKind::Phi)),
+            attempt(load_global_instruction().map(InstKind::LoadGlobal)),
+            attempt(
+                store_global_instruction(ctx).map(|(name, val)| InstKind::StoreGlobal(name, val)),
+            ),
+            attempt(addr_instruction().map(InstKind::Addr)),
+            attempt(alloca_instruction().map(InstKind::Alloca)),
+            attempt(load_instruction(ctx).map(InstKind::Load)),
+            attempt(store_instruction(ctx).map(|(ptr, val)| InstKind::Store { ptr, val })),
+            attempt(
+                offset_instruction(ctx).map(|(ptr, offset)| InstKind::PtrOffset { ptr, offset }),
+            ),
+            attempt(conversion_instruction(ctx)),
         )),
     )
         .map(move |(v, ty, kind)| {
@@ -577,13 +827,103 @@ where
     })
 }
 
+pub fn parse_global<I>() -> impl Parser<I, Output = (SmolStr, GlobalVar)>
+where
+    I: Stream<Token = char>,
+{
+    (
+        string("global").skip(spaces1()),
+        ident().skip(spaces0()),
+        string("=").skip(spaces0()),
+        ty().skip(spaces1()),
+        number().skip(nl1()),
+    )
+        .message("When parsing global variable")
+        .map(|(_, name, _, ty, init)| (name.into(), GlobalVar { ty, init }))
+}
+
+/// Matches an escape sequence inside a string literal, without the leading `\\`
+fn escaped_char<Input>() -> impl Parser<Input, Output = char>
+where
+    Input: Stream<Token = char>,
+{
+    choice((
+        char('n').map(|_| '\n'),
+        char('r').map(|_| '\r'),
+        char('t').map(|_| '\t'),
+        char('0').map(|_| '\0'),
+        char('\\'),
+        char('"'),
+        char('\''),
+        (string("u{"), many1(hex_digit()), char('}')).and_then(|(_, hex, _): (_, String, _)| {
+            u32::from_str_radix(&hex, 16)
+                .ok()
+                .and_then(std::char::from_u32)
+                .ok_or_else(|| {
+                    StreamErrorFor::<Input>::message_format(format_args!(
+                        "invalid unicode escape \\u{{{}}}",
+                        hex
+                    ))
+                })
+        }),
+    ))
+}
+
+/// Matches a double-quoted string literal with Rust-style escapes
+fn string_literal<Input>() -> impl Parser<Input, Output = String>
+where
+    Input: Stream<Token = char>,
+{
+    between(
+        char('"'),
+        char('"'),
+        many(choice((
+            none_of("\\\"".chars()),
+            char('\\').with(escaped_char()),
+        ))),
+    )
+}
+
+pub fn parse_data<I>() -> impl Parser<I, Output = (SmolStr, String)>
+where
+    I: Stream<Token = char>,
+{
+    (
+        string("data").skip(spaces1()),
+        ident().skip(spaces0()),
+        string("=").skip(spaces0()),
+        string_literal().skip(nl1()),
+    )
+        .message("When parsing data")
+        .map(|(_, name, _, data)| (name.into(), data))
+}
+
+pub fn parse_extern<I>() -> impl Parser<I, Output = (SmolStr, Ty)>
+where
+    I: Stream<Token = char>,
+{
+    string("extern")
+        .skip(spaces1())
+        .with(func_header())
+        .skip(nl1())
+        .message("When parsing extern function")
+}
+
 pub fn parse_program<'a, I>() -> impl Parser<I, Output = Program>
 where
     I: Stream<Token = char> + 'a,
 {
     ignore(nl_spaces())
+        .and(many(attempt(parse_global())))
+        .and(many(attempt(parse_data())))
+        .and(many(attempt(parse_extern())))
         .and(many(parse_func().map(|f| (f.name.clone(), f))))
         .skip(nl_spaces())
         .skip(eof())
-        .map(|(_, functions)| Program { functions })
+        .map(|((((_, globals), data), externs), functions)| Program {
+            functions,
+            externs,
+            globals,
+            data,
+        })
 }
@@ -0,0 +1,55 @@
+#![cfg(test)]
+use crate::parser::parse_program_from_string;
+
+#[test]
+fn float_imm_round_trip() {
+    let input = r"
+    fn @main() -> f64 {
+    bb0:
+        %0 = f64 add #100000000000000000000.0 #0.00000000015
+        %1 = f64 add #1.0e300 #-2.5e-300
+        %2 = f64 add #1.5 #0.1
+        %3 = f64 add #inf #-inf
+        %4 = f64 add #nan #0.0
+        return %4
+    }
+    ";
+    let program = parse_program_from_string(input).unwrap();
+    let output = program.to_string();
+    for imm in &[
+        "#1.0e20 #1.5e-10",
+        "#1.0e300 #-2.5e-300",
+        "#1.5 #0.1",
+        "#inf #-inf",
+        "#nan #0.0",
+    ] {
+        assert!(output.contains(imm), "`{}` not found in:\n{}", imm, output);
+    }
+
+    let reparsed = parse_program_from_string(&output).unwrap();
+    assert_eq!(reparsed.to_string(), output);
+}
+
+#[test]
+fn bb_set_before_links_both_ways() {
+    use crate::TacFunc;
+
+    let mut func = TacFunc::new_untyped("f".into());
+    let bb0 = func.bb_new();
+    let bb1 = func.bb_new();
+    let bb2 = func.bb_new();
+    func.bb_set_first(bb0);
+    func.bb_set_after(bb0, bb1);
+    func.bb_set_before(bb1, bb2);
+
+    assert_eq!(func.bb_get(bb0).next, Some(bb2));
+    assert_eq!(func.bb_get(bb2).prev, Some(bb0));
+    assert_eq!(func.bb_get(bb2).next, Some(bb1));
+    // The item we insert before must point back at the inserted item, not
+    // forward to it
+    assert_eq!(func.bb_get(bb1).prev, Some(bb2));
+    assert_eq!(func.bb_get(bb1).next, None);
+
+    let order = func.bb_iter().map(|(id, _)| id).collect::<Vec<_>>();
+    assert_eq!(order, vec![bb0, bb2, bb1]);
+}
@@ -14,6 +14,9 @@ pub enum Ty {
     Unit,
     Func(Arc<FuncTy>),
     Ptr(Arc<Ty>),
+    /// A fixed-length array. Like pointers, values of array types point to
+    /// their first element.
+    Array(Arc<ArrayTy>),
     Numeric(NumericTy),
 }
 
@@ -26,6 +29,10 @@ impl Ty {
         Ty::Numeric(NumericTy::bool())
     }
 
+    pub fn float() -> Ty {
+        Ty::Numeric(NumericTy::float())
+    }
+
     pub fn unit() -> Ty {
         Ty::Unit
     }
@@ -41,11 +48,24 @@ impl Ty {
         Ty::Ptr(Arc::new(ty))
     }
 
+    pub fn array_of(elem: Ty, len: usize) -> Ty {
+        Ty::Array(Arc::new(ArrayTy { elem, len }))
+    }
+
+    /// Returns the type of the elements a pointer or an array points to.
+    pub fn elem(&self) -> Option<&Ty> {
+        match self {
+            Ty::Ptr(elem) => Some(elem),
+            Ty::Array(arr) => Some(&arr.elem),
+            _ => None,
+        }
+    }
+
     pub fn size(&self) -> Option<usize> {
         match self {
             Ty::Unit => Some(0),
             Ty::Func(_) => None,
-            Ty::Ptr(_) => Some(PTR_SIZE),
+            Ty::Ptr(_) | Ty::Array(_) => Some(PTR_SIZE),
             Ty::Numeric(n) => Some(n.size() as usize),
         }
     }
@@ -78,6 +98,13 @@ impl NumericTy {
         }
     }
 
+    pub fn float() -> NumericTy {
+        NumericTy {
+            kind: TyKind::Float,
+            size: 64,
+        }
+    }
+
     pub fn size(&self) -> u8 {
         self.size
     }
@@ -87,6 +114,13 @@ impl NumericTy {
 pub enum TyKind {
     Bool,
     Int,
+    Float,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ArrayTy {
+    pub elem: Ty,
+    pub len: usize,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
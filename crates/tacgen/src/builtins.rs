@@ -0,0 +1,43 @@
+//! Functions provided by the runtime. They are declared in the global scope
+//! before anything else, and calls to them are lowered into intrinsic calls.
+
+use std::collections::HashMap;
+
+use azuki_tac::{Intrinsic, Ty};
+
+use crate::symbol::ScopeBuilder;
+
+/// The type of the given builtin function.
+pub fn ty_of(intrinsic: Intrinsic) -> Ty {
+    match intrinsic {
+        Intrinsic::GetInt | Intrinsic::GetChar => Ty::func_of(Ty::int(), vec![]),
+        Intrinsic::PutInt | Intrinsic::PutChar => Ty::func_of(Ty::unit(), vec![Ty::int()]),
+        Intrinsic::PutStr => Ty::func_of(Ty::unit(), vec![Ty::ptr_of(Ty::int())]),
+        Intrinsic::PutLn => Ty::func_of(Ty::unit(), vec![]),
+    }
+}
+
+/// A registry of builtin functions declared in a scope.
+pub struct Builtins {
+    /// Maps variable IDs of the declared functions to their intrinsics
+    vars: HashMap<u32, Intrinsic>,
+}
+
+impl Builtins {
+    /// Declares all builtin functions in the global scope of `scope`.
+    pub fn declare(scope: &mut ScopeBuilder) -> Builtins {
+        let mut vars = HashMap::new();
+        for &intrinsic in Intrinsic::ALL.iter() {
+            let var = scope
+                .insert_global(&intrinsic.name().into(), ty_of(intrinsic), true)
+                .expect("Builtins should be declared before anything else");
+            vars.insert(var.id, intrinsic);
+        }
+        Builtins { vars }
+    }
+
+    /// Returns the intrinsic the variable refers to, if it is a builtin function.
+    pub fn get(&self, var_id: u32) -> Option<Intrinsic> {
+        self.vars.get(&var_id).copied()
+    }
+}
@@ -0,0 +1,109 @@
+//! Compile-time evaluation of constant expressions, used for initializers of
+//! global variables.
+
+use std::collections::HashMap;
+
+use azuki_syntax::ast::*;
+use smol_str::SmolStr;
+
+use azuki_tac::Ty;
+
+use crate::{
+    assert_type_eq,
+    err::{Error, ErrorKind},
+    resolve_ty,
+};
+
+/// Evaluates `expr` into a constant and its type. Identifiers inside `expr` may
+/// only refer to constants inside `consts`. Operands are type-checked the same
+/// way as in function bodies.
+pub fn eval(expr: &Expr, consts: &HashMap<SmolStr, (i64, Ty)>) -> Result<(i64, Ty), Error> {
+    let not_constant = || Error::new(ErrorKind::NotConstant, expr.span());
+    match expr {
+        Expr::Literal(lit) => match lit.kind {
+            LiteralKind::Integer(i) => Ok((i as i64, Ty::int())),
+            LiteralKind::Bool(b) => Ok((b as i64, Ty::bool())),
+            LiteralKind::Char(ch) => Ok((ch as i64, Ty::int())),
+            _ => Err(not_constant()),
+        },
+        Expr::Ident(ident) => consts.get(&ident.name).cloned().ok_or_else(not_constant),
+        Expr::Unary(expr) => {
+            let (val, ty) = eval(&expr.expr, consts)?;
+            let expected = match expr.op {
+                UnaryOp::Not => Ty::bool(),
+                UnaryOp::Neg | UnaryOp::Pos | UnaryOp::BitNot => Ty::int(),
+            };
+            assert_type_eq(&expected, &ty, expr.expr.span())?;
+            let val = match expr.op {
+                UnaryOp::Neg => val.wrapping_neg(),
+                UnaryOp::Pos => val,
+                UnaryOp::Not => (val == 0) as i64,
+                UnaryOp::BitNot => !val,
+            };
+            Ok((val, ty))
+        }
+        Expr::Binary(expr) => {
+            let (lhs, lhs_ty) = eval(&expr.lhs, consts)?;
+            let (rhs, rhs_ty) = eval(&expr.rhs, consts)?;
+            assert_type_eq(&lhs_ty, &rhs_ty, expr.rhs.span())?;
+            let operand_ty = match expr.op {
+                BinaryOp::Eq | BinaryOp::Neq => lhs_ty.clone(),
+                BinaryOp::And | BinaryOp::Or => Ty::bool(),
+                _ => Ty::int(),
+            };
+            assert_type_eq(&operand_ty, &lhs_ty, expr.lhs.span())?;
+
+            let res = match expr.op {
+                BinaryOp::Add => lhs.wrapping_add(rhs),
+                BinaryOp::Sub => lhs.wrapping_sub(rhs),
+                BinaryOp::Mul => lhs.wrapping_mul(rhs),
+                BinaryOp::Div => lhs.checked_div(rhs).ok_or_else(not_constant)?,
+                BinaryOp::Mod => lhs.checked_rem(rhs).ok_or_else(not_constant)?,
+                BinaryOp::BitAnd => lhs & rhs,
+                BinaryOp::BitOr => lhs | rhs,
+                BinaryOp::Xor => lhs ^ rhs,
+                BinaryOp::Shl => lhs.checked_shl(rhs as u32).ok_or_else(not_constant)?,
+                BinaryOp::Shr => lhs.checked_shr(rhs as u32).ok_or_else(not_constant)?,
+                BinaryOp::Gt => (lhs > rhs) as i64,
+                BinaryOp::Lt => (lhs < rhs) as i64,
+                BinaryOp::Ge => (lhs >= rhs) as i64,
+                BinaryOp::Le => (lhs <= rhs) as i64,
+                BinaryOp::Eq => (lhs == rhs) as i64,
+                BinaryOp::Neq => (lhs != rhs) as i64,
+                BinaryOp::And => (lhs != 0 && rhs != 0) as i64,
+                BinaryOp::Or => (lhs != 0 || rhs != 0) as i64,
+            };
+            let ty = match expr.op {
+                BinaryOp::Add
+                | BinaryOp::Sub
+                | BinaryOp::Mul
+                | BinaryOp::Div
+                | BinaryOp::Mod
+                | BinaryOp::BitAnd
+                | BinaryOp::BitOr
+                | BinaryOp::Xor
+                | BinaryOp::Shl
+                | BinaryOp::Shr => Ty::int(),
+                _ => Ty::bool(),
+            };
+            Ok((res, ty))
+        }
+        Expr::As(expr) => {
+            let (val, from) = eval(&expr.val, consts)?;
+            let to = resolve_ty(&expr.ty)?;
+            if from == to {
+                Ok((val, to))
+            } else if from == Ty::int() && to == Ty::bool() {
+                Ok(((val != 0) as i64, to))
+            } else if from == Ty::bool() && to == Ty::int() {
+                Ok((val, to))
+            } else if from == Ty::int() && to == Ty::float() {
+                // Floating-point values are not supported in constants
+                Err(not_constant())
+            } else {
+                Err(Error::new(ErrorKind::InvalidCast { from, to }, expr.span))
+            }
+        }
+        Expr::Assign(_) | Expr::IncDec(_) | Expr::Call(_) | Expr::Index(_) => Err(not_constant()),
+    }
+}
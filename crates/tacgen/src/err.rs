@@ -1,12 +1,135 @@
-use azuki_tac::Ty;
+use std::fmt::Display;
+
+use azuki_syntax::{diagnostics::Diagnostic, span::Span};
+use azuki_tac::{Ty, TyKind};
 use smol_str::SmolStr;
 
+/// An error found while generating code, pointing at the offending part of the
+/// source code.
+#[derive(Debug)]
+pub struct Error {
+    pub kind: ErrorKind,
+    pub span: Span,
+}
+
+impl Error {
+    pub fn new(kind: ErrorKind, span: Span) -> Self {
+        Self { kind, span }
+    }
+
+    /// Converts this error into a diagnostic for rendering.
+    pub fn to_diagnostic(&self) -> Diagnostic {
+        Diagnostic::error(self.kind.to_string(), Some(self.span))
+    }
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} at {:?}", self.kind, self.span)
+    }
+}
+
 #[derive(Debug)]
-pub enum Error {
+pub enum ErrorKind {
     UnknownType(SmolStr),
+    UnsupportedType(String),
+    CannotIndex(Ty),
     DuplicateVar(SmolStr),
     UnknownVar(SmolStr),
-    InvalidLExpr(String),
-    WrongParamLength { expected: usize, found: usize },
-    TypeMismatch { expected: Ty, found: Ty },
+    NotAFunction(SmolStr),
+    InvalidLExpr,
+    NotConstant,
+    WrongParamLength {
+        expected: usize,
+        found: usize,
+    },
+    TypeMismatch {
+        expected: Ty,
+        found: Ty,
+    },
+    InvalidCast {
+        from: Ty,
+        to: Ty,
+    },
+    AssignToConst(SmolStr),
+    /// Writing into a string literal, which is read-only
+    AssignToStringLiteral,
+    /// Assigning a whole array, which would only copy the pointer to it
+    AssignToArray,
+    /// A non-void function may reach its end without returning a value
+    MissingReturn(SmolStr),
+    BreakOutsideLoop,
+    ContinueOutsideLoop,
+    /// A value of type `void` is used as an operand
+    VoidValue,
+}
+
+impl Display for ErrorKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ErrorKind::UnknownType(name) => write!(f, "unknown type `{}`", name),
+            ErrorKind::UnsupportedType(reason) => write!(f, "unsupported type: {}", reason),
+            ErrorKind::CannotIndex(ty) => {
+                write!(f, "cannot index into a value of type `{}`", SourceTy(ty))
+            }
+            ErrorKind::DuplicateVar(name) => write!(f, "`{}` is already declared", name),
+            ErrorKind::UnknownVar(name) => write!(f, "cannot find `{}` in this scope", name),
+            ErrorKind::NotAFunction(name) => write!(f, "`{}` is not a function", name),
+            ErrorKind::InvalidLExpr => write!(f, "cannot assign to this expression"),
+            ErrorKind::NotConstant => write!(f, "expression is not a constant"),
+            ErrorKind::WrongParamLength { expected, found } => {
+                write!(f, "expected {} arguments, found {}", expected, found)
+            }
+            ErrorKind::TypeMismatch { expected, found } => write!(
+                f,
+                "mismatched types: expected `{}`, found `{}`",
+                SourceTy(expected),
+                SourceTy(found)
+            ),
+            ErrorKind::InvalidCast { from, to } => {
+                write!(f, "cannot cast `{}` as `{}`", SourceTy(from), SourceTy(to))
+            }
+            ErrorKind::AssignToConst(name) => {
+                write!(f, "cannot assign twice to constant `{}`", name)
+            }
+            ErrorKind::AssignToStringLiteral => write!(f, "cannot assign to a string literal"),
+            ErrorKind::AssignToArray => write!(f, "cannot assign to a whole array"),
+            ErrorKind::MissingReturn(name) => write!(
+                f,
+                "function `{}` may reach its end without returning a value",
+                name
+            ),
+            ErrorKind::BreakOutsideLoop => write!(f, "`break` outside of a loop"),
+            ErrorKind::ContinueOutsideLoop => write!(f, "`continue` outside of a loop"),
+            ErrorKind::VoidValue => write!(f, "`void` value used as an operand"),
+        }
+    }
+}
+
+/// Displays a type using the names in source code, e.g. `int` instead of `i32`.
+pub struct SourceTy<'a>(pub &'a Ty);
+
+impl<'a> Display for SourceTy<'a> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.0 {
+            Ty::Unit => write!(f, "void"),
+            Ty::Numeric(ty) => match ty.kind {
+                TyKind::Bool => write!(f, "bool"),
+                TyKind::Int => write!(f, "int"),
+                TyKind::Float => write!(f, "double"),
+            },
+            Ty::Ptr(elem) => write!(f, "[{}]", SourceTy(elem)),
+            Ty::Array(arr) => write!(f, "[{}; {}]", SourceTy(&arr.elem), arr.len),
+            Ty::Func(func) => {
+                write!(f, "fn(")?;
+                for (idx, param) in func.params.iter().enumerate() {
+                    if idx != 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{}", SourceTy(param))?;
+                }
+                write!(f, ") -> {}", SourceTy(&func.return_type))
+            }
+        }
+    }
 }
@@ -1,34 +1,176 @@
+pub mod builtins;
+mod consteval;
 pub mod err;
 pub mod symbol;
 mod test;
 
-use azuki_syntax::{ast::*, visitor::AstVisitor};
+use azuki_syntax::{ast::*, span::Span, visitor::AstVisitor};
 use azuki_tac as tac;
-use err::Error;
-
-use std::{cell::RefCell, collections::HashMap, rc::Rc, todo};
+use err::{Error, ErrorKind};
+
+use builtins::Builtins;
+use smol_str::SmolStr;
+use std::{
+    cell::RefCell,
+    collections::{BTreeMap, HashMap, HashSet},
+    rc::Rc,
+};
 use symbol::{NumberingCounter, ScopeBuilder, StringInterner};
 
 use tac::{
-    builder::FuncBuilder, BBId, BinaryInst, Branch, FunctionCall, Inst, InstId, InstKind, TacFunc,
-    Ty, Value,
+    builder::FuncBuilder, BBId, BinaryInst, Branch, FunctionCall, Inst, InstId, InstKind,
+    IntrinsicCall, TacFunc, Ty, Value,
 };
 
-pub fn compile(tac: &Program) -> Result<tac::Program, Error> {
+/// Compiles the program into TAC. Compilation continues past errors as far as
+/// possible, so that all errors in the program are reported at once.
+pub fn compile(tac: &Program) -> Result<tac::Program, Vec<Error>> {
+    compile_files(std::slice::from_ref(tac))
+}
+
+/// Compiles a program made of multiple files into TAC, e.g. the ones returned
+/// by [`SourceMap::parse_with_imports`](azuki_syntax::source_map::SourceMap::parse_with_imports).
+///
+/// All files share the same global scope, so global items may not be defined
+/// more than once across files. Global variables are declared in the order of
+/// the files.
+pub fn compile_files(programs: &[Program]) -> Result<tac::Program, Vec<Error>> {
     let interner = Rc::new(RefCell::new(StringInterner::new()));
     let counter = Rc::new(NumberingCounter::new(0));
     let global_scope_builder = Rc::new(RefCell::new(ScopeBuilder::new(counter, interner.clone())));
+    let builtins = Rc::new(Builtins::declare(&mut global_scope_builder.borrow_mut()));
+    let mut errors = vec![];
+
+    let mut globals = BTreeMap::new();
+    let mut consts = HashMap::new();
+    for decl in programs.iter().flat_map(|p| &p.decls) {
+        let name = interner.borrow_mut().intern(&decl.name.name);
+        let ty = match global_ty(&decl.ty) {
+            Ok(ty) => ty,
+            Err(e) => {
+                errors.push(e);
+                continue;
+            }
+        };
+        let init = match &decl.val {
+            Some(val) => consteval::eval(val, &consts)
+                .and_then(|(init, init_ty)| {
+                    assert_type_eq(&ty, &init_ty, val.span())?;
+                    Ok(init)
+                })
+                .unwrap_or_else(|e| {
+                    errors.push(e);
+                    0
+                }),
+            None => 0,
+        };
+        if global_scope_builder
+            .borrow_mut()
+            .insert_global(&name, ty.clone(), decl.is_const)
+            .is_none()
+        {
+            errors.push(Error::new(
+                ErrorKind::DuplicateVar(name.clone()),
+                decl.name.span,
+            ));
+            continue;
+        }
+        if decl.is_const {
+            consts.insert(name.clone(), (init, ty.clone()));
+        }
+        globals.insert(name, tac::GlobalVar { ty, init });
+    }
+
+    // Extern functions only have a signature, and calls to them are left for
+    // the runtime to resolve
+    let mut externs = BTreeMap::new();
+    for func in programs.iter().flat_map(|p| &p.externs) {
+        let ty = match func_ty_of(&func.params, &func.ret_ty) {
+            Ok(ty) => ty,
+            Err(e) => {
+                errors.push(e);
+                continue;
+            }
+        };
+        if global_scope_builder
+            .borrow_mut()
+            .insert_global(&func.name.name, ty.clone(), true)
+            .is_none()
+        {
+            errors.push(Error::new(
+                ErrorKind::DuplicateVar(func.name.name.clone()),
+                func.name.span,
+            ));
+            continue;
+        }
+        externs.insert(func.name.name.clone(), ty);
+    }
+
+    // Collect signatures of all functions first, so that functions can be
+    // called before they are defined. Functions with invalid signatures are
+    // not compiled.
+    let mut declared_funcs = vec![];
+    for func in programs.iter().flat_map(|p| &p.funcs) {
+        let ty = match func_ty_of(&func.params, &func.ret_ty) {
+            Ok(ty) => ty,
+            Err(e) => {
+                errors.push(e);
+                continue;
+            }
+        };
+        if global_scope_builder
+            .borrow_mut()
+            .insert_global(&func.name.name, ty, true)
+            .is_none()
+        {
+            errors.push(Error::new(
+                ErrorKind::DuplicateVar(func.name.name.clone()),
+                func.name.span,
+            ));
+            continue;
+        }
+        declared_funcs.push(func);
+    }
 
+    let data = Rc::new(RefCell::new(BTreeMap::new()));
     let mut funcs = HashMap::new();
-    for func in &tac.funcs {
+    for func in declared_funcs {
         let name = func.name.name.clone();
         let mut result = TacFunc::new_untyped(name.clone());
-        let mut compiler =
-            FuncCompiler::new(&mut result, interner.clone(), global_scope_builder.clone());
-        compiler.visit_func(func)?;
+        let mut compiler = FuncCompiler::new(
+            &mut result,
+            interner.clone(),
+            global_scope_builder.clone(),
+            builtins.clone(),
+            data.clone(),
+        );
+        if let Err(e) = compiler.visit_func(func) {
+            errors.push(e);
+        }
+        errors.append(&mut compiler.errors);
         funcs.insert(name, result);
     }
-    Ok(tac::Program { functions: funcs })
+
+    if !errors.is_empty() {
+        errors.sort_by_key(|e| (e.span.file, e.span.start()));
+        return Err(errors);
+    }
+    Ok(tac::Program {
+        functions: funcs,
+        externs,
+        globals,
+        data: data.take(),
+    })
+}
+
+/// The place an assignment writes into.
+pub enum LValue {
+    /// A local variable, identified by its variable ID
+    Local(u32),
+    /// A global variable, identified by its name
+    Global(SmolStr),
+    /// A place in memory, identified by a pointer
+    Ptr(Value),
 }
 
 struct BreakTarget {
@@ -50,6 +192,14 @@ pub struct FuncCompiler<'a> {
     interner: Rc<RefCell<StringInterner>>,
 
     scope_builder: Rc<RefCell<ScopeBuilder>>,
+
+    builtins: Rc<Builtins>,
+
+    /// Read-only data of the whole program, shared between functions
+    data: Rc<RefCell<BTreeMap<SmolStr, String>>>,
+
+    /// Errors that the compiler has recovered from
+    errors: Vec<Error>,
 }
 
 impl<'a> FuncCompiler<'a> {
@@ -57,6 +207,8 @@ impl<'a> FuncCompiler<'a> {
         func: &'a mut TacFunc,
         interner: Rc<RefCell<StringInterner>>,
         scope_builder: Rc<RefCell<ScopeBuilder>>,
+        builtins: Rc<Builtins>,
+        data: Rc<RefCell<BTreeMap<SmolStr, String>>>,
     ) -> FuncCompiler<'a> {
         FuncCompiler {
             builder: FuncBuilder::new_func(func),
@@ -64,7 +216,29 @@ impl<'a> FuncCompiler<'a> {
             return_ty: Ty::unit(),
             interner,
             scope_builder,
+            builtins,
+            data,
+            errors: vec![],
+        }
+    }
+
+    /// Records an error and continues compiling.
+    fn report(&mut self, result: Result<(), Error>) {
+        if let Err(e) = result {
+            self.errors.push(e);
+        }
+    }
+
+    /// Returns the name of the read-only data holding `s`. Identical strings
+    /// share the same data.
+    fn intern_data(&mut self, s: &str) -> SmolStr {
+        let mut data = self.data.borrow_mut();
+        if let Some((name, _)) = data.iter().find(|(_, d)| d.as_str() == s) {
+            return name.clone();
         }
+        let name = SmolStr::new(format!("$str{}", data.len()));
+        data.insert(name.clone(), s.into());
+        name
     }
 
     fn visit_func_param_real(
@@ -75,8 +249,13 @@ impl<'a> FuncCompiler<'a> {
         let ty = self.visit_ty(&param.ty)?;
         let mut scope = self.scope_builder.borrow_mut();
         let var = scope
-            .insert(&param.name.name, ty.clone())
-            .ok_or_else(|| Error::DuplicateVar(param.name.name.clone()))?;
+            .insert(&param.name.name, ty.clone(), param.is_const)
+            .ok_or_else(|| {
+                Error::new(
+                    ErrorKind::DuplicateVar(param.name.name.clone()),
+                    param.name.span,
+                )
+            })?;
 
         let val = self.builder.insert_after_current_place(Inst {
             kind: InstKind::Param(idx),
@@ -88,6 +267,392 @@ impl<'a> FuncCompiler<'a> {
 
         Ok((val, ty))
     }
+
+    /// Allocates `count` elements in the entry block of this function, so that
+    /// allocations inside loops don't run more than once.
+    fn insert_alloca(&mut self, count: usize, ty: Ty) -> InstId {
+        let inst = Inst {
+            kind: InstKind::Alloca(count),
+            ty,
+        };
+        let entry_bb = self.builder.func.starting_block().unwrap();
+        if self.builder.current_bb_id() == entry_bb {
+            self.builder.insert_after_current_place(inst)
+        } else {
+            self.builder.insert_at_start_of(inst, entry_bb).unwrap()
+        }
+    }
+
+    /// Visits an expression whose result is used as a value, e.g. an operand or
+    /// a function argument. Such expressions cannot have a type of `void`.
+    fn visit_value_expr(&mut self, expr: &Expr) -> Result<(Value, Ty), Error> {
+        let (val, ty) = self.visit_expr(expr)?;
+        if ty == Ty::unit() {
+            return Err(Error::new(ErrorKind::VoidValue, expr.span()));
+        }
+        Ok((val, ty))
+    }
+
+    /// Builds `lhs op rhs` out of already evaluated operands, which come from
+    /// `lhs_expr` and `rhs_expr`. Logical operators short-circuit, so they are
+    /// handled by `visit_logical_expr` instead.
+    fn build_binary(
+        &mut self,
+        op: BinaryOp,
+        (lhsv, lhst): (Value, Ty),
+        (rhsv, rhst): (Value, Ty),
+        lhs_expr: &Expr,
+        rhs_expr: &Expr,
+    ) -> Result<(Value, Ty), Error> {
+        assert_type_eq(&lhst, &rhst, rhs_expr.span())?;
+
+        // `==` and `!=` accept operands of any type, arithmetic and ordering
+        // accept integers and floats, and the rest only accept integers.
+        let ty = match op {
+            BinaryOp::Eq | BinaryOp::Neq => Ty::bool(),
+            BinaryOp::Gt | BinaryOp::Lt | BinaryOp::Ge | BinaryOp::Le => {
+                assert_arith_ty(&lhst, lhs_expr.span())?;
+                Ty::bool()
+            }
+            BinaryOp::Add | BinaryOp::Sub | BinaryOp::Mul | BinaryOp::Div => {
+                assert_arith_ty(&lhst, lhs_expr.span())?;
+                lhst
+            }
+            _ => {
+                assert_type_eq(&Ty::int(), &lhst, lhs_expr.span())?;
+                lhst
+            }
+        };
+
+        let v = self.builder.insert_after_current_place(Inst {
+            kind: InstKind::Binary(BinaryInst {
+                op: match op {
+                    BinaryOp::Add => tac::BinaryOp::Add,
+                    BinaryOp::Sub => tac::BinaryOp::Sub,
+                    BinaryOp::Mul => tac::BinaryOp::Mul,
+                    BinaryOp::Div => tac::BinaryOp::Div,
+                    BinaryOp::Mod => tac::BinaryOp::Mod,
+                    BinaryOp::BitAnd => tac::BinaryOp::And,
+                    BinaryOp::BitOr => tac::BinaryOp::Or,
+                    BinaryOp::Xor => tac::BinaryOp::Xor,
+                    BinaryOp::Shl => tac::BinaryOp::Shl,
+                    BinaryOp::Shr => tac::BinaryOp::Shr,
+                    BinaryOp::Gt => tac::BinaryOp::Gt,
+                    BinaryOp::Lt => tac::BinaryOp::Lt,
+                    BinaryOp::Ge => tac::BinaryOp::Ge,
+                    BinaryOp::Le => tac::BinaryOp::Le,
+                    BinaryOp::Eq => tac::BinaryOp::Eq,
+                    BinaryOp::Neq => tac::BinaryOp::Ne,
+                    BinaryOp::And | BinaryOp::Or => unreachable!(),
+                },
+                lhs: lhsv,
+                rhs: rhsv,
+            }),
+            ty: ty.clone(),
+        });
+
+        Ok((v.into(), ty))
+    }
+
+    /// Checks that the assignment target `lhs` is not a constant.
+    fn check_not_const(&self, lhs: &Expr, span: Span) -> Result<(), Error> {
+        if let Expr::Ident(ident) = lhs {
+            let is_const = matches!(
+                self.scope_builder.borrow().find(&ident.name),
+                Some(var) if var.is_const
+            );
+            if is_const {
+                return Err(Error::new(
+                    ErrorKind::AssignToConst(ident.name.clone()),
+                    span,
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// Reads the current value of the place.
+    fn read_lvalue(&mut self, lvalue: &LValue, ty: &Ty) -> Value {
+        let kind = match lvalue {
+            LValue::Local(id) => return self.builder.read_variable_cur(*id).unwrap().into(),
+            LValue::Global(name) => InstKind::LoadGlobal(name.clone()),
+            LValue::Ptr(ptr) => InstKind::Load(*ptr),
+        };
+        let val = self.builder.insert_after_current_place(Inst {
+            kind,
+            ty: ty.clone(),
+        });
+        val.into()
+    }
+
+    /// Writes `val` of type `ty` into the place, and returns the instruction
+    /// doing so.
+    fn write_lvalue(&mut self, lvalue: LValue, val: Value, ty: Ty) -> InstId {
+        let var_id = match lvalue {
+            LValue::Local(id) => id,
+            LValue::Global(name) => {
+                return self.builder.insert_after_current_place(Inst {
+                    kind: InstKind::StoreGlobal(name, val),
+                    ty: Ty::unit(),
+                });
+            }
+            LValue::Ptr(ptr) => {
+                return self.builder.insert_after_current_place(Inst {
+                    kind: InstKind::Store { ptr, val },
+                    ty: Ty::unit(),
+                });
+            }
+        };
+
+        let result_idx = match val {
+            Value::Dest(i) => i,
+            Value::Imm(_) | Value::FloatImm(_) => self.builder.insert_after_current_place(Inst {
+                kind: InstKind::Assign(val),
+                ty,
+            }),
+        };
+        self.builder.write_variable_cur(var_id, result_idx).unwrap();
+        result_idx
+    }
+
+    /// Checks whether `bb` can be reached from the entry of this function.
+    fn is_reachable(&self, bb: BBId) -> bool {
+        let entry = self.builder.func.starting_block().unwrap();
+        let mut visited = HashSet::new();
+        let mut stack = vec![entry];
+        while let Some(cur) = stack.pop() {
+            if cur == bb {
+                return true;
+            }
+            if visited.insert(cur) {
+                stack.extend(self.builder.succ_of_bb(cur));
+            }
+        }
+        false
+    }
+
+    /// Computes the address of the element `expr` refers to. Returns the
+    /// pointer and the type of the element.
+    fn visit_index_addr(&mut self, expr: &IndexExpr) -> Result<(Value, Ty), Error> {
+        let (base, base_ty) = self.visit_value_expr(&expr.base)?;
+        let elem_ty = match base_ty.elem() {
+            Some(elem) => elem.clone(),
+            None => {
+                return Err(Error::new(
+                    ErrorKind::CannotIndex(base_ty),
+                    expr.base.span(),
+                ))
+            }
+        };
+        let (index, index_ty) = self.visit_value_expr(&expr.index)?;
+        assert_type_eq(&Ty::int(), &index_ty, expr.index.span())?;
+
+        let ptr = self.builder.insert_after_current_place(Inst {
+            kind: InstKind::PtrOffset {
+                ptr: base,
+                offset: index,
+            },
+            ty: Ty::ptr_of(elem_ty.clone()),
+        });
+        Ok((ptr.into(), elem_ty))
+    }
+
+    /// Lowers a loop. `while` loops have no `step`, and a missing `cond` loops
+    /// forever. `continue` jumps to the step block if there is one, or directly
+    /// back to the condition otherwise.
+    ///
+    /// ```text
+    /// cur_bb --> cond_bb --> loop_bb ... loop_end_bb --> step_bb --> cond_bb
+    ///              \---> next_bb
+    /// ```
+    fn visit_loop(
+        &mut self,
+        cond: Option<&Expr>,
+        step: Option<&Expr>,
+        body: &BlockStmt,
+    ) -> Result<(), Error> {
+        let cur_bb = self.builder.current_bb_id();
+        let cond_bb = self.builder.new_bb();
+        self.builder
+            .add_branch(Branch::Jump(empty_jump_target(cond_bb)), cur_bb)
+            .unwrap();
+
+        self.builder.mark_sealed(cur_bb);
+        self.builder.mark_filled(cur_bb);
+
+        self.builder.set_current_bb(cond_bb);
+        self.builder.func.bb_set_after(cur_bb, cond_bb);
+
+        let cond = match cond {
+            Some(cond) => {
+                let (val, ty) = self.visit_value_expr(cond)?;
+                assert_type_eq(&Ty::bool(), &ty, cond.span())?;
+                Some(val)
+            }
+            None => None,
+        };
+        // Short-circuiting conditions may end in another basic block
+        let cond_end_bb = self.builder.current_bb_id();
+
+        let loop_bb = self.builder.new_bb();
+        self.builder.func.bb_set_after(cond_end_bb, loop_bb);
+        let next_bb = self.builder.new_bb();
+        let step_bb = step.map(|_| self.builder.new_bb());
+
+        self.break_targets.push(BreakTarget {
+            break_out: next_bb,
+            continue_in: step_bb.unwrap_or(cond_bb),
+        });
+
+        self.builder.mark_filled(cond_end_bb);
+
+        // cond_bb --> loop_bb
+        //   \---> next_bb
+        match cond {
+            Some(cond) => {
+                self.builder
+                    .add_branch(
+                        Branch::CondJump {
+                            cond,
+                            target: empty_jump_target(loop_bb),
+                        },
+                        cond_end_bb,
+                    )
+                    .unwrap();
+                self.builder
+                    .add_branch(Branch::Jump(empty_jump_target(next_bb)), cond_end_bb)
+                    .unwrap();
+            }
+            None => {
+                self.builder
+                    .add_branch(Branch::Jump(empty_jump_target(loop_bb)), cond_end_bb)
+                    .unwrap();
+            }
+        }
+
+        self.builder.set_current_bb(loop_bb);
+        self.visit_block_stmt(body)?;
+        let mut loop_end_bb = self.builder.current_bb_id();
+
+        if let (Some(step), Some(step_bb)) = (step, step_bb) {
+            self.builder
+                .add_branch(Branch::Jump(empty_jump_target(step_bb)), loop_end_bb)
+                .unwrap();
+            self.builder.mark_sealed(loop_end_bb);
+            self.builder.mark_filled(loop_end_bb);
+
+            self.builder.func.bb_set_after(loop_end_bb, step_bb);
+            self.builder.set_current_bb(step_bb);
+            let res = self.visit_expr(step).map(|_| ());
+            self.report(res);
+            loop_end_bb = self.builder.current_bb_id();
+        }
+
+        self.builder
+            .add_branch(Branch::Jump(empty_jump_target(cond_bb)), loop_end_bb)
+            .unwrap();
+
+        self.builder.mark_sealed(loop_end_bb);
+        self.builder.mark_filled(loop_end_bb);
+        self.builder.mark_sealed(cond_bb);
+
+        self.break_targets.pop();
+
+        self.builder.func.bb_set_after(loop_end_bb, next_bb);
+        self.builder.set_current_bb(next_bb);
+
+        Ok(())
+    }
+
+    /// Lowers `&&` and `||` with short-circuit evaluation. The right hand side
+    /// is evaluated in its own basic block, and the result is merged with a phi.
+    ///
+    /// ```text
+    /// cur_bb:
+    ///     %short = #0             (#1 for `||`)
+    ///     br rhs_bb if %lhs       (br next_bb if %lhs for `||`)
+    ///     br next_bb              (br rhs_bb for `||`)
+    /// rhs_bb:
+    ///     %rhs = (...)
+    ///     br next_bb
+    /// next_bb:
+    ///     %res = phi [(%short, cur_bb), (%rhs, rhs_bb)]
+    /// ```
+    fn visit_logical_expr(&mut self, expr: &BinaryExpr) -> Result<(Value, Ty), Error> {
+        let is_and = matches!(expr.op, BinaryOp::And);
+        let ty = Ty::bool();
+
+        let (lhsv, lhst) = self.visit_value_expr(&expr.lhs)?;
+        assert_type_eq(&ty, &lhst, expr.lhs.span())?;
+
+        let short_circuit = self.builder.insert_after_current_place(Inst {
+            kind: InstKind::Assign(Value::Imm(if is_and { 0 } else { 1 })),
+            ty: ty.clone(),
+        });
+
+        let cur_bb = self.builder.current_bb_id();
+        let rhs_bb = self.builder.new_bb();
+        let next_bb = self.builder.new_bb();
+        self.builder.func.bb_set_after(cur_bb, rhs_bb);
+
+        let (on_true, on_false) = if is_and {
+            (rhs_bb, next_bb)
+        } else {
+            (next_bb, rhs_bb)
+        };
+        self.builder
+            .add_branch(
+                Branch::CondJump {
+                    cond: lhsv,
+                    target: empty_jump_target(on_true),
+                },
+                cur_bb,
+            )
+            .unwrap();
+        self.builder
+            .add_branch(Branch::Jump(empty_jump_target(on_false)), cur_bb)
+            .unwrap();
+        self.builder.mark_filled(cur_bb);
+        self.builder.mark_sealed(cur_bb);
+
+        self.builder.set_current_bb(rhs_bb);
+        self.builder.mark_sealed(rhs_bb);
+        let (rhsv, rhst) = self.visit_value_expr(&expr.rhs)?;
+        assert_type_eq(&ty, &rhst, expr.rhs.span())?;
+
+        // Phi sources must be instructions
+        let rhs_val = match rhsv {
+            Value::Dest(i) => i,
+            Value::Imm(_) | Value::FloatImm(_) => self.builder.insert_after_current_place(Inst {
+                kind: InstKind::Assign(rhsv),
+                ty: ty.clone(),
+            }),
+        };
+
+        let rhs_end_bb = self.builder.current_bb_id();
+        self.builder
+            .add_branch(Branch::Jump(empty_jump_target(next_bb)), rhs_end_bb)
+            .unwrap();
+        self.builder.mark_filled(rhs_end_bb);
+        self.builder.mark_sealed(rhs_end_bb);
+
+        self.builder.func.bb_set_after(rhs_end_bb, next_bb);
+        self.builder.mark_sealed(next_bb);
+
+        let phi = self.builder.insert_phi(next_bb, ty.clone()).unwrap();
+        let sources = self
+            .builder
+            .func
+            .inst_get_mut(phi)
+            .kind
+            .as_phi_mut()
+            .unwrap();
+        sources.insert(cur_bb, short_circuit);
+        sources.insert(rhs_end_bb, rhs_val);
+        self.builder.set_current_bb(next_bb);
+
+        Ok((phi.into(), ty))
+    }
 }
 
 // This implementation is the main tac-generation part.
@@ -101,7 +666,7 @@ impl<'a> FuncCompiler<'a> {
 //   have all their predecessors determined. Any statement visitor method could mark the input basic
 //   block as filled and sealed.
 impl<'a> AstVisitor for FuncCompiler<'a> {
-    type LExprResult = Result<(u32, Ty), Error>;
+    type LExprResult = Result<(LValue, Ty), Error>;
 
     type ExprResult = Result<(Value, Ty), Error>;
 
@@ -113,53 +678,93 @@ impl<'a> AstVisitor for FuncCompiler<'a> {
 
     type FuncResult = Result<(), Error>;
 
+    fn default_lexpr_result() -> Self::LExprResult {
+        unreachable!("FuncCompiler visits every left-hand side expression itself")
+    }
+
+    fn default_expr_result() -> Self::ExprResult {
+        unreachable!("FuncCompiler visits every expression itself")
+    }
+
+    fn default_ty_result() -> Self::TyResult {
+        unreachable!("FuncCompiler visits every type itself")
+    }
+
+    fn default_stmt_result() -> Self::StmtResult {
+        Ok(())
+    }
+
+    fn default_program_result() -> Self::ProgramResult {}
+
+    fn default_func_result() -> Self::FuncResult {
+        Ok(())
+    }
+
     fn visit_func(&mut self, func: &FuncStmt) -> Self::FuncResult {
-        self.scope_builder.borrow_mut().add_scope();
         let initial = self.builder.new_bb();
         self.builder.set_current_bb(initial);
         self.builder.func.bb_set_first(initial);
 
         let return_ty = self.visit_ty(&func.ret_ty)?;
+        self.scope_builder.borrow_mut().add_scope();
         let mut params_ty = vec![];
         for (idx, param) in func.params.iter().enumerate() {
-            let (_param_op, param_ty) = self.visit_func_param_real(param, idx)?;
-            params_ty.push(param_ty);
+            match self.visit_func_param_real(param, idx) {
+                Ok((_param_op, param_ty)) => params_ty.push(param_ty),
+                Err(e) => self.errors.push(e),
+            }
         }
+        self.return_ty = return_ty.clone();
+        // The function itself is already declared in `compile`
         let func_ty = Ty::func_of(return_ty, params_ty);
-        self.builder.set_type(func_ty.clone());
-
-        let func_name = &func.name.name;
-        self.scope_builder
-            .borrow_mut()
-            .insert_global(func_name, func_ty);
-
-        self.visit_block_stmt(&func.body)?;
+        self.builder.set_type(func_ty);
+
+        let res = self.visit_block_stmt(&func.body);
+        self.report(res);
+
+        // Falling off the end returns nothing, which is only valid for
+        // functions returning `void`. A statement that failed to compile may
+        // have been a return, so this is only checked for error-free bodies.
+        let end_bb = self.builder.current_bb_id();
+        if self.is_reachable(end_bb) {
+            if self.return_ty != Ty::unit() && self.errors.is_empty() {
+                self.errors.push(Error::new(
+                    ErrorKind::MissingReturn(func.name.name.clone()),
+                    func.span,
+                ));
+            }
+            self.builder
+                .add_branch(Branch::Return(None), end_bb)
+                .unwrap();
+        }
 
-        self.builder.mark_sealed(self.builder.current_bb_id());
-        self.builder.mark_filled(self.builder.current_bb_id());
+        self.builder.mark_sealed(end_bb);
+        self.builder.mark_filled(end_bb);
 
         self.scope_builder.borrow_mut().pop_scope().unwrap();
         Ok(())
     }
 
-    fn visit_ty(&mut self, _ty: &TyDef) -> Self::TyResult {
-        match _ty.name.as_str() {
-            "void" => Ok(Ty::Unit),
-            "int" => Ok(Ty::int()),
-            _ => Err(Error::UnknownType(_ty.name.clone())),
-        }
+    fn visit_ty(&mut self, ty: &TyDef) -> Self::TyResult {
+        resolve_ty(ty)
     }
 
-    fn visit_literal_expr(&mut self, _expr: &LiteralExpr) -> Self::ExprResult {
-        match _expr.kind {
-            LiteralKind::Integer(val) => Ok((Value::Imm(val as i64), Ty::int())),
-            LiteralKind::Float(_) => {
-                todo!("implement float (or not)")
-            }
-            LiteralKind::String(_) => {
-                todo!("Implement String")
+    fn visit_literal_expr(&mut self, expr: &LiteralExpr) -> Self::ExprResult {
+        match &expr.kind {
+            LiteralKind::Integer(val) => Ok((Value::Imm(*val as i64), Ty::int())),
+            LiteralKind::Bool(val) => Ok((Value::Imm(*val as i64), Ty::bool())),
+            LiteralKind::Float(val) => Ok((Value::FloatImm((*val).into()), Ty::float())),
+            LiteralKind::String(s) => {
+                // Strings are zero-terminated arrays of characters
+                let ty = Ty::array_of(Ty::int(), s.chars().count() + 1);
+                let name = self.intern_data(s);
+                let val = self.builder.insert_after_current_place(Inst {
+                    kind: InstKind::Addr(name),
+                    ty: ty.clone(),
+                });
+                Ok((val.into(), ty))
             }
-            LiteralKind::Char(ch) => Ok((Value::Imm(ch as i64), Ty::int())),
+            LiteralKind::Char(ch) => Ok((Value::Imm(*ch as i64), Ty::int())),
         }
     }
 
@@ -167,84 +772,131 @@ impl<'a> AstVisitor for FuncCompiler<'a> {
         let scope = self.scope_builder.borrow();
         let var = scope
             .find(&expr.name)
-            .ok_or_else(|| Error::UnknownVar(expr.name.clone()))?;
+            .ok_or_else(|| Error::new(ErrorKind::UnknownVar(expr.name.clone()), expr.span))?;
+        if var.is_global {
+            let name = self.interner.borrow_mut().intern(&expr.name);
+            let val = self.builder.insert_after_current_place(Inst {
+                kind: InstKind::LoadGlobal(name),
+                ty: var.ty.clone(),
+            });
+            return Ok((val.into(), var.ty.clone()));
+        }
         let val = self.builder.read_variable_cur(var.id).unwrap();
         Ok((val.into(), var.ty.clone()))
     }
 
     fn visit_assign_expr(&mut self, expr: &AssignExpr) -> Self::ExprResult {
-        let (var_id, var_ty) = self.visit_lexpr(&expr.lhs)?;
-        let (val, val_ty) = self.visit_expr(&expr.rhs)?;
-
-        assert_type_eq(&var_ty, &val_ty)?;
+        if !expr.allow_assign_const {
+            self.check_not_const(&expr.lhs, expr.span)?;
+        }
 
-        let result_idx = match val {
-            Value::Dest(i) => {
-                self.builder.write_variable_cur(var_id, i).unwrap();
-                i
-            }
-            Value::Imm(i) => {
-                let target = self.builder.insert_after_current_place(Inst {
-                    kind: InstKind::Assign(Value::Imm(i)),
-                    ty: val_ty,
-                });
-                self.builder.write_variable_cur(var_id, target).unwrap();
-                target
+        let (lvalue, var_ty) = self.visit_lexpr(&expr.lhs)?;
+        if var_ty.as_array().is_some() {
+            return Err(Error::new(ErrorKind::AssignToArray, expr.span));
+        }
+        let (val, val_ty) = match expr.op {
+            // `a op= b` is `a = a op b`, except that `a` is only evaluated once
+            Some(op) => {
+                let cur = self.read_lvalue(&lvalue, &var_ty);
+                let rhs = self.visit_value_expr(&expr.rhs)?;
+                self.build_binary(op, (cur, var_ty.clone()), rhs, &expr.lhs, &expr.rhs)?
             }
+            None => self.visit_value_expr(&expr.rhs)?,
         };
+
+        assert_type_eq(&var_ty, &val_ty, expr.rhs.span())?;
+
+        let result_idx = self.write_lvalue(lvalue, val, val_ty);
         Ok((result_idx.into(), Ty::unit()))
     }
 
+    fn visit_inc_dec_expr(&mut self, expr: &IncDecExpr) -> Self::ExprResult {
+        self.check_not_const(&expr.expr, expr.span)?;
+
+        let (lvalue, ty) = self.visit_lexpr(&expr.expr)?;
+        assert_arith_ty(&ty, expr.expr.span())?;
+
+        let old = self.read_lvalue(&lvalue, &ty);
+        let one = if ty == Ty::float() {
+            Value::FloatImm(1.0.into())
+        } else {
+            Value::Imm(1)
+        };
+        let new = self.builder.insert_after_current_place(Inst {
+            kind: InstKind::Binary(BinaryInst {
+                op: match expr.op {
+                    IncDecOp::Inc => tac::BinaryOp::Add,
+                    IncDecOp::Dec => tac::BinaryOp::Sub,
+                },
+                lhs: old,
+                rhs: one,
+            }),
+            ty: ty.clone(),
+        });
+        self.write_lvalue(lvalue, new.into(), ty.clone());
+
+        let val = if expr.is_prefix { new.into() } else { old };
+        Ok((val, ty))
+    }
+
     fn visit_lexpr(&mut self, expr: &Expr) -> Self::LExprResult {
         let expr = match expr {
             Expr::Ident(i) => i,
-            _ => return Err(Error::InvalidLExpr(format!("{:?}", &expr))),
+            Expr::Index(i) => {
+                // String literals live in read-only data
+                if let Expr::Literal(LiteralExpr {
+                    kind: LiteralKind::String(_),
+                    ..
+                }) = &*i.base
+                {
+                    return Err(Error::new(ErrorKind::AssignToStringLiteral, i.span));
+                }
+                let (ptr, ty) = self.visit_index_addr(i)?;
+                return Ok((LValue::Ptr(ptr), ty));
+            }
+            _ => return Err(Error::new(ErrorKind::InvalidLExpr, expr.span())),
         };
         let scope = self.scope_builder.borrow();
         let var = scope
             .find(&expr.name)
-            .ok_or_else(|| Error::UnknownVar(expr.name.clone()))?;
-        Ok((var.id, var.ty.clone()))
+            .ok_or_else(|| Error::new(ErrorKind::UnknownVar(expr.name.clone()), expr.span))?;
+        let lvalue = if var.is_global {
+            LValue::Global(self.interner.borrow_mut().intern(&expr.name))
+        } else {
+            LValue::Local(var.id)
+        };
+        Ok((lvalue, var.ty.clone()))
     }
 
     fn visit_binary_expr(&mut self, expr: &BinaryExpr) -> Self::ExprResult {
-        let (lhsv, lhst) = self.visit_expr(&expr.lhs)?;
-        let (rhsv, rhst) = self.visit_expr(&expr.rhs)?;
-
-        assert_type_eq(&lhst, &rhst)?;
-
-        let v = self.builder.insert_after_current_place(Inst {
-            kind: InstKind::Binary(BinaryInst {
-                op: match expr.op {
-                    BinaryOp::Add => tac::BinaryOp::Add,
-                    BinaryOp::Sub => tac::BinaryOp::Sub,
-                    BinaryOp::Mul => tac::BinaryOp::Mul,
-                    BinaryOp::Div => tac::BinaryOp::Div,
-                    BinaryOp::Gt => tac::BinaryOp::Gt,
-                    BinaryOp::Lt => tac::BinaryOp::Lt,
-                    BinaryOp::Ge => tac::BinaryOp::Ge,
-                    BinaryOp::Le => tac::BinaryOp::Le,
-                    BinaryOp::Eq => tac::BinaryOp::Eq,
-                    BinaryOp::Neq => tac::BinaryOp::Ne,
-                },
-                lhs: lhsv,
-                rhs: rhsv,
-            }),
-            ty: lhst.clone(),
-        });
+        if matches!(expr.op, BinaryOp::And | BinaryOp::Or) {
+            return self.visit_logical_expr(expr);
+        }
 
-        Ok((v.into(), lhst))
+        let lhs = self.visit_value_expr(&expr.lhs)?;
+        let rhs = self.visit_value_expr(&expr.rhs)?;
+        self.build_binary(expr.op, lhs, rhs, &expr.lhs, &expr.rhs)
     }
 
     fn visit_unary_expr(&mut self, expr: &UnaryExpr) -> Self::ExprResult {
-        let (v, t) = self.visit_expr(&expr.expr)?;
+        let (v, t) = self.visit_value_expr(&expr.expr)?;
+        match expr.op {
+            UnaryOp::Not => assert_type_eq(&Ty::bool(), &t, expr.expr.span())?,
+            UnaryOp::BitNot => assert_type_eq(&Ty::int(), &t, expr.expr.span())?,
+            UnaryOp::Neg | UnaryOp::Pos => assert_arith_ty(&t, expr.expr.span())?,
+        }
 
         match expr.op {
             UnaryOp::Neg => {
+                let zero = if t == Ty::float() {
+                    Value::FloatImm(0.0.into())
+                } else {
+                    Value::Imm(0)
+                };
                 let v = self.builder.insert_after_current_place(Inst {
                     kind: InstKind::Binary(BinaryInst {
                         op: tac::BinaryOp::Sub,
-                        lhs: Value::Imm(0),
+                        lhs: zero,
                         rhs: v,
                     }),
                     ty: t.clone(),
@@ -252,43 +904,95 @@ impl<'a> AstVisitor for FuncCompiler<'a> {
                 Ok((v.into(), t))
             }
             UnaryOp::Pos => Ok((v, t)),
+            UnaryOp::Not => {
+                let v = self.builder.insert_after_current_place(Inst {
+                    kind: InstKind::Binary(BinaryInst {
+                        op: tac::BinaryOp::Eq,
+                        lhs: v,
+                        rhs: Value::Imm(0),
+                    }),
+                    ty: t.clone(),
+                });
+                Ok((v.into(), t))
+            }
+            UnaryOp::BitNot => {
+                let v = self.builder.insert_after_current_place(Inst {
+                    kind: InstKind::Binary(BinaryInst {
+                        op: tac::BinaryOp::Xor,
+                        lhs: v,
+                        rhs: Value::Imm(-1),
+                    }),
+                    ty: t.clone(),
+                });
+                Ok((v.into(), t))
+            }
         }
     }
 
+    fn visit_index_expr(&mut self, expr: &IndexExpr) -> Self::ExprResult {
+        let (ptr, ty) = self.visit_index_addr(expr)?;
+        let val = self.builder.insert_after_current_place(Inst {
+            kind: InstKind::Load(ptr),
+            ty: ty.clone(),
+        });
+        Ok((val.into(), ty))
+    }
+
     fn visit_call_expr(&mut self, expr: &CallExpr) -> Self::ExprResult {
-        let func_ty = self
-            .scope_builder
-            .borrow()
-            .find(&expr.func.name)
-            .ok_or_else(|| Error::UnknownVar(expr.func.name.clone()))?
-            .ty
-            .clone();
+        let (func_ty, intrinsic) = {
+            let scope = self.scope_builder.borrow();
+            let var = scope.find(&expr.func.name).ok_or_else(|| {
+                Error::new(
+                    ErrorKind::UnknownVar(expr.func.name.clone()),
+                    expr.func.span,
+                )
+            })?;
+            (var.ty.clone(), self.builtins.get(var.id))
+        };
 
-        let func_ty = func_ty.as_func().unwrap();
+        let func_ty = func_ty.as_func().ok_or_else(|| {
+            Error::new(
+                ErrorKind::NotAFunction(expr.func.name.clone()),
+                expr.func.span,
+            )
+        })?;
 
         let mut params = vec![];
         let mut types = vec![];
         for subexpr in &expr.params {
-            let (val, ty) = self.visit_expr(&subexpr)?;
+            let (val, ty) = self.visit_value_expr(&subexpr)?;
             params.push(val);
             types.push(ty);
         }
 
         if types.len() != func_ty.params.len() {
-            return Err(Error::WrongParamLength {
-                expected: func_ty.params.len(),
-                found: types.len(),
-            });
+            return Err(Error::new(
+                ErrorKind::WrongParamLength {
+                    expected: func_ty.params.len(),
+                    found: types.len(),
+                },
+                expr.span,
+            ));
         }
-        for (ty, expected) in types.iter().zip(func_ty.params.iter()) {
-            assert_type_eq(ty, expected)?;
+        for ((ty, expected), param) in types.iter().zip(func_ty.params.iter()).zip(&expr.params) {
+            // Arrays decay into pointers to their first element, e.g. when
+            // passed to `putstr`
+            let ty = match (expected, ty) {
+                (Ty::Ptr(elem), Ty::Array(arr)) if **elem == arr.elem => expected,
+                _ => ty,
+            };
+            assert_type_eq(expected, ty, param.span())?;
         }
 
-        let val = self.builder.insert_after_current_place(Inst {
-            kind: InstKind::FunctionCall(FunctionCall {
+        let kind = match intrinsic {
+            Some(intrinsic) => InstKind::Intrinsic(IntrinsicCall { intrinsic, params }),
+            None => InstKind::FunctionCall(FunctionCall {
                 name: self.interner.borrow_mut().intern(&expr.func.name),
                 params,
             }),
+        };
+        let val = self.builder.insert_after_current_place(Inst {
+            kind,
             ty: func_ty.return_type.clone(),
         });
 
@@ -296,81 +1000,65 @@ impl<'a> AstVisitor for FuncCompiler<'a> {
     }
 
     fn visit_as_expr(&mut self, expr: &AsExpr) -> Self::ExprResult {
-        self.visit_expr(&expr.val)
+        let (val, from) = self.visit_value_expr(&expr.val)?;
+        let to = self.visit_ty(&expr.ty)?;
+        if from == to {
+            return Ok((val, to));
+        }
+
+        let kind = if from == Ty::int() && to == Ty::bool() {
+            InstKind::Binary(BinaryInst {
+                op: tac::BinaryOp::Ne,
+                lhs: val,
+                rhs: Value::Imm(0),
+            })
+        } else if from == Ty::bool() && to == Ty::int() {
+            // Booleans are already represented as 0 or 1
+            InstKind::Assign(val)
+        } else if from == Ty::int() && to == Ty::float() {
+            InstKind::IntToFloat(val)
+        } else if from == Ty::float() && to == Ty::int() {
+            InstKind::FloatToInt(val)
+        } else {
+            return Err(Error::new(ErrorKind::InvalidCast { from, to }, expr.span));
+        };
+        let val = self.builder.insert_after_current_place(Inst {
+            kind,
+            ty: to.clone(),
+        });
+        Ok((val.into(), to))
     }
 
     fn visit_block_stmt(&mut self, stmt: &BlockStmt) -> Self::StmtResult {
         self.scope_builder.borrow_mut().add_scope();
         for substmt in &stmt.stmts {
-            self.visit_stmt(substmt)?;
+            // Errors inside a statement don't affect the following ones
+            let res = self.visit_stmt(substmt);
+            self.report(res);
         }
         self.scope_builder.borrow_mut().pop_scope().unwrap();
         Ok(())
     }
 
     fn visit_while_stmt(&mut self, stmt: &WhileStmt) -> Self::StmtResult {
-        let cur_bb = self.builder.current_bb_id();
-        let cond_bb = self.builder.new_bb();
-        self.builder
-            .add_branch(Branch::Jump(empty_jump_target(cond_bb)), cur_bb)
-            .unwrap();
-
-        self.builder.mark_sealed(cur_bb);
-        self.builder.mark_filled(cur_bb);
-
-        self.builder.set_current_bb(cond_bb);
-        self.builder.func.bb_set_after(cur_bb, cond_bb);
-
-        let (cond, _cond_ty) = self.visit_expr(&stmt.cond)?;
-
-        let loop_bb = self.builder.new_bb();
-        self.builder.func.bb_set_after(loop_bb, cond_bb);
-        let next_bb = self.builder.new_bb();
-
-        self.break_targets.push(BreakTarget {
-            break_out: next_bb,
-            continue_in: cond_bb,
-        });
-
-        self.builder.mark_filled(cond_bb);
-
-        // cond_bb --> loop_bb
-        //   \---> next_bb
-        self.builder
-            .add_branch(
-                Branch::CondJump {
-                    cond,
-                    target: empty_jump_target(loop_bb),
-                },
-                cond_bb,
-            )
-            .unwrap();
-
-        self.builder.set_current_bb(loop_bb);
-        self.visit_block_stmt(&stmt.body)?;
-        let loop_end_bb = self.builder.current_bb_id();
-
-        self.builder
-            .add_branch(Branch::Jump(empty_jump_target(cond_bb)), loop_end_bb)
-            .unwrap();
-
-        self.builder.mark_sealed(loop_end_bb);
-        self.builder.mark_filled(loop_end_bb);
-        self.builder.mark_sealed(cond_bb);
-
-        self.break_targets.pop();
-
-        self.builder
-            .add_branch(Branch::Jump(empty_jump_target(next_bb)), cond_bb)
-            .unwrap();
-        self.builder.func.bb_set_after(loop_end_bb, next_bb);
-        self.builder.set_current_bb(next_bb);
+        self.visit_loop(Some(&stmt.cond), None, &stmt.body)
+    }
 
-        Ok(())
+    fn visit_for_stmt(&mut self, stmt: &ForStmt) -> Self::StmtResult {
+        // Variables declared in `init` are only visible inside the loop
+        self.scope_builder.borrow_mut().add_scope();
+        if let Some(init) = &stmt.init {
+            let res = self.visit_stmt(init);
+            self.report(res);
+        }
+        let res = self.visit_loop(stmt.cond.as_deref(), stmt.step.as_deref(), &stmt.body);
+        self.scope_builder.borrow_mut().pop_scope().unwrap();
+        res
     }
 
     fn visit_if_stmt(&mut self, stmt: &IfStmt) -> Self::StmtResult {
-        let expr_val = self.visit_expr(&stmt.cond)?;
+        let expr_val = self.visit_value_expr(&stmt.cond)?;
+        assert_type_eq(&Ty::bool(), &expr_val.1, stmt.cond.span())?;
         let last_bb = self.builder.current_bb_id();
 
         self.builder.mark_filled(last_bb);
@@ -464,15 +1152,33 @@ impl<'a> AstVisitor for FuncCompiler<'a> {
         let var_id = self
             .scope_builder
             .borrow_mut()
-            .insert(&stmt.name.name, ty.clone())
-            .ok_or_else(|| Error::DuplicateVar(stmt.name.name.clone()))?
+            .insert(&stmt.name.name, ty.clone(), stmt.is_const)
+            .ok_or_else(|| {
+                Error::new(
+                    ErrorKind::DuplicateVar(stmt.name.name.clone()),
+                    stmt.name.span,
+                )
+            })?
             .id;
-        self.builder.declare_var(var_id, ty);
+        self.builder.declare_var(var_id, ty.clone());
+
+        if let TyDefKind::Array { len, .. } = stmt.ty.kind {
+            if stmt.val.is_some() {
+                return Err(Error::new(
+                    ErrorKind::UnsupportedType("arrays cannot have initializers".into()),
+                    stmt.span,
+                ));
+            }
+            let ptr = self.insert_alloca(len as usize, ty);
+            self.builder.write_variable_cur(var_id, ptr).unwrap();
+            return Ok(());
+        }
 
         if let Some(expr) = &stmt.val {
             let (inst, _) = self.visit_assign_expr(&AssignExpr {
                 span: stmt.span,
                 allow_assign_const: stmt.is_const,
+                op: None,
                 lhs: Rc::new(Expr::Ident(Ident {
                     span: stmt.span,
                     name: stmt.name.name.clone(),
@@ -489,8 +1195,11 @@ impl<'a> AstVisitor for FuncCompiler<'a> {
 
     fn visit_return_stmt(&mut self, stmt: &ReturnStmt) -> Self::StmtResult {
         let val = if let Some(val) = &stmt.val {
-            Some(self.visit_expr(&val)?)
+            let (v, ty) = self.visit_value_expr(&val)?;
+            assert_type_eq(&self.return_ty, &ty, val.span())?;
+            Some((v, ty))
         } else {
+            assert_type_eq(&self.return_ty, &Ty::unit(), stmt.span)?;
             None
         };
 
@@ -509,8 +1218,12 @@ impl<'a> AstVisitor for FuncCompiler<'a> {
         Ok(())
     }
 
-    fn visit_break_stmt(&mut self, _span: azuki_syntax::span::Span) -> Self::StmtResult {
-        let continue_target = self.break_targets.last().unwrap().break_out;
+    fn visit_break_stmt(&mut self, span: azuki_syntax::span::Span) -> Self::StmtResult {
+        let continue_target = self
+            .break_targets
+            .last()
+            .ok_or_else(|| Error::new(ErrorKind::BreakOutsideLoop, span))?
+            .break_out;
 
         let cur_bb = self.builder.current_bb_id();
         self.builder
@@ -526,8 +1239,12 @@ impl<'a> AstVisitor for FuncCompiler<'a> {
         Ok(())
     }
 
-    fn visit_continue_stmt(&mut self, _span: azuki_syntax::span::Span) -> Self::StmtResult {
-        let continue_target = self.break_targets.last().unwrap().continue_in;
+    fn visit_continue_stmt(&mut self, span: azuki_syntax::span::Span) -> Self::StmtResult {
+        let continue_target = self
+            .break_targets
+            .last()
+            .ok_or_else(|| Error::new(ErrorKind::ContinueOutsideLoop, span))?
+            .continue_in;
 
         let cur_bb = self.builder.current_bb_id();
         self.builder
@@ -546,14 +1263,84 @@ impl<'a> AstVisitor for FuncCompiler<'a> {
     fn visit_empty_stmt(&mut self, _span: azuki_syntax::span::Span) -> Self::StmtResult {
         Ok(())
     }
+
+    fn visit_error_stmt(&mut self, _span: azuki_syntax::span::Span) -> Self::StmtResult {
+        // Programs containing parse errors should never reach code generation
+        Ok(())
+    }
 }
 
-fn assert_type_eq(lhs: &Ty, rhs: &Ty) -> Result<(), err::Error> {
-    if lhs != rhs {
-        return Err(Error::TypeMismatch {
-            expected: lhs.clone(),
-            found: rhs.clone(),
-        });
+/// Resolves the type of a global variable. Only scalar types are supported.
+fn global_ty(ty: &TyDef) -> Result<Ty, Error> {
+    let unsupported = |reason: &str| {
+        Err(Error::new(
+            ErrorKind::UnsupportedType(reason.into()),
+            ty.span,
+        ))
+    };
+    if let TyDefKind::Array { .. } = ty.kind {
+        return unsupported("global arrays are not supported");
+    }
+    let res = resolve_ty(ty)?;
+    if res == Ty::float() {
+        return unsupported("global floating-point variables are not supported");
+    }
+    Ok(res)
+}
+
+/// Resolves the type of a function with the given signature.
+fn func_ty_of(params: &[FuncParam], ret_ty: &TyDef) -> Result<Ty, Error> {
+    let params = params
+        .iter()
+        .map(|param| resolve_ty(&param.ty))
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(Ty::func_of(resolve_ty(ret_ty)?, params))
+}
+
+/// Resolves the type definition into a TAC type. Arrays keep their length, so
+/// arrays of different lengths have different types.
+fn resolve_ty(ty: &TyDef) -> Result<Ty, Error> {
+    match &ty.kind {
+        TyDefKind::Named(name) => match name.as_str() {
+            "void" => Ok(Ty::Unit),
+            "int" => Ok(Ty::int()),
+            "bool" => Ok(Ty::bool()),
+            "double" => Ok(Ty::float()),
+            _ => Err(Error::new(ErrorKind::UnknownType(name.clone()), ty.span)),
+        },
+        TyDefKind::Array { elem, len } => {
+            let elem = resolve_ty(elem)?;
+            if elem.elem().is_some() {
+                return Err(Error::new(
+                    ErrorKind::UnsupportedType("arrays of arrays are not supported".into()),
+                    ty.span,
+                ));
+            }
+            Ok(Ty::array_of(elem, *len as usize))
+        }
+    }
+}
+
+/// Checks that `found` is a type that arithmetic operators accept, i.e. an
+/// integer or a floating-point number.
+fn assert_arith_ty(found: &Ty, span: Span) -> Result<(), err::Error> {
+    if *found == Ty::float() {
+        Ok(())
+    } else {
+        assert_type_eq(&Ty::int(), found, span)
+    }
+}
+
+/// Checks that the type `found` of the expression at `span` equals `expected`.
+fn assert_type_eq(expected: &Ty, found: &Ty, span: Span) -> Result<(), err::Error> {
+    if expected != found {
+        return Err(Error::new(
+            ErrorKind::TypeMismatch {
+                expected: expected.clone(),
+                found: found.clone(),
+            },
+            span,
+        ));
     }
     Ok(())
 }
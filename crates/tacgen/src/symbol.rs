@@ -97,6 +97,8 @@ pub struct Variable {
     pub id: u32,
     /// The type of this variable
     pub ty: Ty,
+    /// Whether if this variable cannot be assigned to after declaration
+    pub is_const: bool,
 }
 
 pub struct ScopeBuilder {
@@ -131,26 +133,28 @@ impl ScopeBuilder {
 
     /// Insert a variable with given name and type into this scope. Returns a reference to the
     /// inserted variable if succeeded, and `None` if failed.
-    pub fn insert(&mut self, name: &SmolStr, ty: Ty) -> Option<&Variable> {
+    pub fn insert(&mut self, name: &SmolStr, ty: Ty, is_const: bool) -> Option<&Variable> {
         let interned_name = self.interner.borrow_mut().intern(name);
         let var_id = self.counter.next();
         let variable = Variable {
             is_global: self.is_top_scope_global(),
             id: var_id,
             ty,
+            is_const,
         };
 
         let scope = self.top_scope_mut();
         scope.insert(interned_name, variable)
     }
 
-    pub fn insert_global(&mut self, name: &SmolStr, ty: Ty) -> Option<&Variable> {
+    pub fn insert_global(&mut self, name: &SmolStr, ty: Ty, is_const: bool) -> Option<&Variable> {
         let interned_name = self.interner.borrow_mut().intern(name);
         let var_id = self.counter.next();
         let variable = Variable {
             is_global: true,
             id: var_id,
             ty,
+            is_const,
         };
 
         let scope = self.global_scope_mut();
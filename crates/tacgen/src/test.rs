@@ -3,6 +3,8 @@
 use azuki_syntax::parse;
 use azuki_tac::parser::EasyParser;
 
+use crate::err::ErrorKind;
+
 #[test]
 fn test_basic_func_generation() {
     let input = r"
@@ -34,3 +36,375 @@ fn test_basic_func_generation() {
         }
     }
 }
+
+#[test]
+fn test_bool_type_check() {
+    let input = r"
+    fn f(x: int) -> int {
+        if x {
+            return 1;
+        }
+        return 0;
+    }
+    ";
+    let program = parse(input).unwrap();
+    match crate::compile(&program) {
+        Err(errors) => match &errors[0].kind {
+            ErrorKind::TypeMismatch { expected, found } => {
+                assert_eq!(*expected, azuki_tac::Ty::bool());
+                assert_eq!(*found, azuki_tac::Ty::int());
+            }
+            other => panic!("expected a type mismatch, got {:?}", other),
+        },
+        Ok(_) => panic!("expected a type mismatch"),
+    }
+
+    let input = r"
+    fn f(x: int) -> bool {
+        return x as bool && !(x > 10);
+    }
+    ";
+    let program = parse(input).unwrap();
+    assert!(crate::compile(&program).is_ok());
+}
+
+#[test]
+fn test_duplicate_func() {
+    let input = r"
+    fn f() -> int {
+        return 1;
+    }
+    fn f() -> int {
+        return 2;
+    }
+    ";
+    let program = parse(input).unwrap();
+    let errors = crate::compile(&program).unwrap_err();
+    assert_eq!(errors.len(), 1);
+    assert!(matches!(&errors[0].kind, ErrorKind::DuplicateVar(name) if name == "f"));
+}
+
+/// Compiles `input` and returns the kind of the first error.
+fn compile_err(input: &str) -> ErrorKind {
+    let program = parse(input).unwrap();
+    match crate::compile(&program) {
+        Ok(_) => panic!("expected an error when compiling:\n{}", input),
+        Err(mut errors) => errors.remove(0).kind,
+    }
+}
+
+#[test]
+fn test_semantic_checks() {
+    let e = compile_err(
+        r"
+        fn f(x: int) -> int {
+            if x > 0 {
+                return 1;
+            }
+        }
+        ",
+    );
+    assert!(matches!(e, ErrorKind::MissingReturn(func) if func == "f"));
+
+    let e = compile_err(
+        r"
+        fn f(const x: int) -> int {
+            x = 1;
+            return x;
+        }
+        ",
+    );
+    assert!(matches!(e, ErrorKind::AssignToConst(name) if name == "x"));
+
+    let e = compile_err(
+        r"
+        const g: int = 1;
+        fn f() -> void {
+            const y: int = 2;
+            g = y;
+        }
+        ",
+    );
+    assert!(matches!(e, ErrorKind::AssignToConst(name) if name == "g"));
+
+    let e = compile_err(
+        r"
+        fn f() -> void {
+            break;
+        }
+        ",
+    );
+    assert!(matches!(e, ErrorKind::BreakOutsideLoop));
+
+    let e = compile_err(
+        r"
+        fn f() -> void {
+            if true {
+                continue;
+            }
+        }
+        ",
+    );
+    assert!(matches!(e, ErrorKind::ContinueOutsideLoop));
+
+    let e = compile_err(
+        r"
+        fn g() -> void {}
+        fn f() -> int {
+            return g() + 1;
+        }
+        ",
+    );
+    assert!(matches!(e, ErrorKind::VoidValue));
+
+    // All paths return, and void functions may fall off the end
+    let input = r"
+    fn f(x: int) -> int {
+        if x > 0 {
+            return 1;
+        } else {
+            return 2;
+        }
+    }
+    fn g() -> void {
+        putint(f(1));
+    }
+    ";
+    let program = parse(input).unwrap();
+    assert!(crate::compile(&program).is_ok());
+}
+
+#[test]
+fn test_multiple_errors() {
+    let input = r"
+    fn f() -> int {
+        let x: int = y;
+        x = true;
+        break;
+        return x;
+    }
+    fn g() -> void {
+        return h();
+    }
+    ";
+    let program = parse(input).unwrap();
+    let errors = crate::compile(&program).unwrap_err();
+    let kinds: Vec<_> = errors.iter().map(|e| e.kind.to_string()).collect();
+    assert_eq!(
+        kinds,
+        vec![
+            "cannot find `y` in this scope",
+            "mismatched types: expected `int`, found `bool`",
+            "`break` outside of a loop",
+            "cannot find `h` in this scope",
+        ]
+    );
+
+    // Every error points at the offending piece of source code
+    let y = input.find("y;").unwrap();
+    assert_eq!(errors[0].span.start(), y);
+    let rendered = azuki_syntax::diagnostics::DiagnosticRenderer::new("test", input)
+        .render(&errors[0].to_diagnostic());
+    assert!(rendered.contains("cannot find `y` in this scope"));
+}
+
+#[test]
+fn test_compile_imported_files() {
+    use azuki_syntax::{source_map::SourceMap, span::FileId};
+
+    let lib = r#"
+    const SCALE: int = 10;
+    fn scale(x: int) -> int { return x * SCALE; }
+    "#;
+    let main = r#"
+    import "lib.c0";
+    fn main() -> void { putint(scale(4)); }
+    "#;
+    let mut files = SourceMap::new();
+    let main_file = files.add("main.c0", main);
+    let load = |_: &std::path::Path| Ok(lib.to_owned());
+    let (programs, errors) = files.parse_with_imports(main_file, load);
+    assert!(errors.is_empty());
+    let result = crate::compile_files(&programs).unwrap();
+    assert!(result.functions.contains_key("scale"));
+    assert!(result.functions.contains_key("main"));
+
+    // Global items may only be defined once across all files
+    let main = r#"
+    import "lib.c0";
+    fn scale(x: int) -> int { return x; }
+    "#;
+    let mut files = SourceMap::new();
+    let main_file = files.add("main.c0", main);
+    let (programs, _) = files.parse_with_imports(main_file, load);
+    let errors = crate::compile_files(&programs).unwrap_err();
+    assert_eq!(errors.len(), 1);
+    assert!(matches!(&errors[0].kind, ErrorKind::DuplicateVar(name) if name == "scale"));
+    // Imported files come first, so the definition in the main file is the
+    // duplicate one
+    assert_eq!(errors[0].span.file, FileId(0));
+}
+
+#[test]
+fn test_extern_funcs() {
+    let input = r"
+    extern fn add(a: int, b: int) -> int;
+    fn main() -> void { putint(add(1, 2)); }
+    ";
+    let program = parse(input).unwrap();
+    let result = crate::compile(&program).unwrap();
+    assert_eq!(
+        result.externs["add"],
+        azuki_tac::Ty::func_of(
+            azuki_tac::Ty::int(),
+            vec![azuki_tac::Ty::int(), azuki_tac::Ty::int()]
+        )
+    );
+    // Externs have no body to compile
+    assert!(!result.functions.contains_key("add"));
+    assert!(result.to_string().contains("call @add (#1, #2)"));
+
+    // Calls are checked against the declared signature
+    let input = r"
+    extern fn add(a: int, b: int) -> int;
+    extern fn putint(x: int) -> void;
+    fn main() -> void { add(1); add(1, true); }
+    ";
+    let program = parse(input).unwrap();
+    let errors = crate::compile(&program).unwrap_err();
+    let kinds = errors.iter().map(|e| &e.kind).collect::<Vec<_>>();
+    assert_eq!(kinds.len(), 3);
+    assert!(matches!(kinds[0], ErrorKind::DuplicateVar(name) if name == "putint"));
+    assert!(matches!(
+        kinds[1],
+        ErrorKind::WrongParamLength {
+            expected: 2,
+            found: 1
+        }
+    ));
+    assert!(matches!(kinds[2], ErrorKind::TypeMismatch { .. }));
+}
+
+#[test]
+fn test_compound_assign_errors() {
+    let input = r"
+    const N: int = 1;
+    fn main() -> void {
+        let b: bool = true;
+        let x: double = 1.0;
+        N += 1;
+        N++;
+        b++;
+        x %= 2.0;
+        x += 1;
+    }
+    ";
+    let program = parse(input).unwrap();
+    let errors = crate::compile(&program).unwrap_err();
+    let kinds = errors.iter().map(|e| &e.kind).collect::<Vec<_>>();
+    assert_eq!(kinds.len(), 5, "{:?}", kinds);
+    assert!(matches!(kinds[0], ErrorKind::AssignToConst(name) if name == "N"));
+    assert!(matches!(kinds[1], ErrorKind::AssignToConst(name) if name == "N"));
+    for kind in &kinds[2..] {
+        assert!(matches!(kind, ErrorKind::TypeMismatch { .. }), "{:?}", kind);
+    }
+    // `x %= 2.0` is reported at `x`, just like `x % 2.0` would be
+    let x = input.find("x %=").unwrap();
+    assert_eq!(errors[3].span.start(), x);
+}
+
+#[test]
+fn test_global_init_type_check() {
+    let input = r"
+    const ONE: int = 1;
+    const YES: bool = ONE == 1;
+    let a: int = ONE + 2;
+    let b: bool = !YES || ONE > 0;
+    let c: int = true as int;
+    let x: bool = 5;
+    let y: int = true;
+    let z: int = YES + 1;
+    let w: bool = ONE;
+    ";
+    let program = parse(input).unwrap();
+    let errors = crate::compile(&program).unwrap_err();
+    let kinds = errors.iter().map(|e| &e.kind).collect::<Vec<_>>();
+    assert_eq!(kinds.len(), 4, "{:?}", kinds);
+    for kind in &kinds {
+        assert!(matches!(kind, ErrorKind::TypeMismatch { .. }), "{:?}", kind);
+    }
+    assert_eq!(errors[0].span.start(), input.find("5;").unwrap());
+    assert_eq!(errors[1].span.start(), input.find("true;").unwrap());
+    // Operands are checked like in function bodies, so `YES + 1` is reported
+    // at `1`
+    let one = input.find("YES + 1").unwrap() + "YES + ".len();
+    assert_eq!(errors[2].span.start(), one);
+
+    let input = r"
+    const ONE: int = 1;
+    const YES: bool = ONE == 1;
+    let a: int = ONE + 2;
+    let b: bool = !YES || ONE > 0;
+    let c: int = true as int;
+    ";
+    let program = parse(input).unwrap();
+    let result = crate::compile(&program).unwrap();
+    assert_eq!(result.globals["YES"].ty, azuki_tac::Ty::bool());
+    assert_eq!(result.globals["YES"].init, 1);
+    assert_eq!(result.globals["a"].init, 3);
+    assert_eq!(result.globals["b"].init, 1);
+    assert_eq!(result.globals["c"].init, 1);
+}
+
+#[test]
+fn test_assign_to_string_literal() {
+    let input = r#"
+    fn main() -> void {
+        "abc"[0] = 1;
+        "abc"[1] += 1;
+        "abc"[2]++;
+        putint("abc"[0]);
+    }
+    "#;
+    let program = parse(input).unwrap();
+    let errors = crate::compile(&program).unwrap_err();
+    let kinds = errors.iter().map(|e| &e.kind).collect::<Vec<_>>();
+    assert_eq!(kinds.len(), 3, "{:?}", kinds);
+    for kind in &kinds {
+        assert!(
+            matches!(kind, ErrorKind::AssignToStringLiteral),
+            "{:?}",
+            kind
+        );
+    }
+}
+
+#[test]
+fn test_array_type_check() {
+    let input = r"
+    fn main() -> void {
+        let a: [int; 3];
+        let b: [int; 3];
+        a = b;
+    }
+    ";
+    let program = parse(input).unwrap();
+    let errors = crate::compile(&program).unwrap_err();
+    assert_eq!(errors.len(), 1, "{:?}", errors);
+    assert!(matches!(errors[0].kind, ErrorKind::AssignToArray));
+    assert_eq!(errors[0].span.start(), input.find("a = b").unwrap());
+
+    // Arrays of different lengths have different types
+    assert!(matches!(
+        compile_err(
+            r"
+            fn f(a: [int; 100]) -> void {}
+            fn main() -> void {
+                let a: [int; 3];
+                f(a);
+            }
+            "
+        ),
+        ErrorKind::TypeMismatch { .. }
+    ));
+}
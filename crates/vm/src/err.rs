@@ -0,0 +1,33 @@
+use std::fmt::Display;
+
+use smol_str::SmolStr;
+
+/// An error that stops the program running inside the virtual machine.
+#[derive(Debug, Clone, PartialEq)]
+pub enum VmError {
+    /// A pointer is moved or dereferenced outside the allocation it points into
+    OutOfBounds { ptr: i64 },
+    /// A store targets read-only data
+    WriteToReadOnly { ptr: usize },
+    /// An extern function is called, but the host didn't provide it
+    MissingExtern(SmolStr),
+    /// `getint` is called, but the input doesn't start with an integer
+    InvalidInput,
+}
+
+impl Display for VmError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            VmError::OutOfBounds { ptr } => write!(f, "Memory access out of bounds at {}", ptr),
+            VmError::WriteToReadOnly { ptr } => {
+                write!(f, "Cannot write into read-only data at {}", ptr)
+            }
+            VmError::MissingExtern(name) => {
+                write!(f, "Extern function `{}` is not provided", name)
+            }
+            VmError::InvalidInput => write!(f, "Failed to read an integer from input"),
+        }
+    }
+}
+
+impl std::error::Error for VmError {}
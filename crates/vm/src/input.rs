@@ -0,0 +1,68 @@
+use std::io::{ErrorKind, Read};
+
+/// A byte-oriented reader supporting one byte of lookahead, used by input
+/// intrinsics.
+pub struct Input<'a> {
+    reader: Box<dyn Read + 'a>,
+    peeked: Option<u8>,
+}
+
+impl<'a> Input<'a> {
+    pub fn new(reader: impl Read + 'a) -> Input<'a> {
+        Input {
+            reader: Box::new(reader),
+            peeked: None,
+        }
+    }
+
+    /// Returns the next byte without consuming it, or `None` at the end of input.
+    pub fn peek(&mut self) -> Option<u8> {
+        if self.peeked.is_none() {
+            let mut buf = [0u8];
+            self.peeked = loop {
+                match self.reader.read(&mut buf) {
+                    Ok(0) => break None,
+                    Ok(_) => break Some(buf[0]),
+                    Err(e) if e.kind() == ErrorKind::Interrupted => continue,
+                    Err(e) => panic!("Failed to read input: {}", e),
+                }
+            };
+        }
+        self.peeked
+    }
+
+    /// Consumes the next byte, or returns `None` at the end of input.
+    pub fn next_byte(&mut self) -> Option<u8> {
+        let res = self.peek();
+        self.peeked = None;
+        res
+    }
+
+    /// Reads a decimal integer with an optional sign, skipping leading
+    /// whitespaces. Returns `None` if the input does not start with an integer.
+    pub fn read_int(&mut self) -> Option<i64> {
+        while self.peek().is_some_and(|b| b.is_ascii_whitespace()) {
+            self.next_byte();
+        }
+
+        let neg = match self.peek() {
+            Some(b'-') => {
+                self.next_byte();
+                true
+            }
+            Some(b'+') => {
+                self.next_byte();
+                false
+            }
+            _ => false,
+        };
+
+        let mut res: Option<i64> = None;
+        while let Some(digit) = self.peek().filter(u8::is_ascii_digit) {
+            self.next_byte();
+            let digit = (digit - b'0') as i64;
+            res = Some(res.unwrap_or(0).wrapping_mul(10).wrapping_add(digit));
+        }
+        res.map(|x| if neg { x.wrapping_neg() } else { x })
+    }
+}
@@ -6,7 +6,7 @@ use std::{
 
 use azuki_tac::{Branch, FunctionCall, Inst, TacFunc};
 
-use crate::Frame;
+use crate::{value::Value, Frame};
 
 /// Trait for collecting data about the code running.
 pub trait Inspector {
@@ -15,7 +15,7 @@ pub trait Inspector {
     /// Called before every instruction is runned.
     fn before_branch(&mut self, inst: &Branch, frame: &Frame);
     /// Called before every function call.
-    fn before_call(&mut self, params: &[i64], func: &TacFunc);
+    fn before_call(&mut self, params: &[Value], func: &TacFunc);
     /// Called after every function is returned
     fn before_ret(&mut self, frame: &Frame);
 }
@@ -33,7 +33,7 @@ where
         self.borrow_mut().before_branch(inst, frame)
     }
 
-    fn before_call(&mut self, params: &[i64], func: &TacFunc) {
+    fn before_call(&mut self, params: &[Value], func: &TacFunc) {
         self.borrow_mut().before_call(params, func)
     }
 
@@ -1,15 +1,48 @@
-use std::{cell::RefCell, collections::HashMap, rc::Rc};
-
-use azuki_tac::{BBId, BinaryInst, Inst, InstId, Program, TacFunc, Value};
+use std::{
+    cell::RefCell,
+    collections::HashMap,
+    convert::TryFrom,
+    io::{Read, Write},
+    rc::Rc,
+};
+
+use azuki_tac::{BBId, BinaryInst, Inst, InstId, Intrinsic, IntrinsicCall, Program, TacFunc, Ty};
+use err::VmError;
+use input::Input;
 use inspector::Inspector;
+use smol_str::SmolStr;
+use value::Value;
 
+pub mod err;
+mod input;
 pub mod inspector;
 mod test;
 pub mod value;
 
+/// The implementation of an extern function, provided by the host. It's called
+/// with the values of the parameters, and returns `None` if the function
+/// returns nothing.
+pub type ExternFn<'src> = Box<dyn FnMut(&[Value]) -> Option<Value> + 'src>;
+
 pub struct Vm<'src> {
     program: &'src Program,
+    externs: HashMap<SmolStr, ExternFn<'src>>,
     stack: Vec<Frame<'src>>,
+    globals: HashMap<SmolStr, Value>,
+    /// Memory allocated by `alloca` instructions. Every slot holds one value,
+    /// and pointers are indices into this list. Read-only data is placed at
+    /// the start of the memory.
+    memory: Vec<Value>,
+    /// Base address and length of every allocation in memory, sorted by their
+    /// base addresses. Pointers may only move and access inside the allocation
+    /// they point into.
+    allocations: Vec<(usize, usize)>,
+    /// Addresses of read-only data
+    data: HashMap<SmolStr, usize>,
+    /// End of read-only data in memory
+    data_end: usize,
+    input: Input<'src>,
+    output: Box<dyn Write + 'src>,
     inspectors: Vec<Rc<RefCell<dyn Inspector>>>,
 }
 
@@ -17,19 +50,28 @@ pub struct Frame<'f> {
     func: &'f TacFunc,
     last_bb: BBId,
     bb: BBId,
-    params: Vec<i64>,
+    params: Vec<Value>,
     instruction: CurrInst,
-    vars: HashMap<InstId, i64>,
+    vars: HashMap<InstId, Value>,
+    /// Length of the VM memory when entering this frame
+    mem_base: usize,
 }
 
 impl<'f> Frame<'f> {
-    pub fn eval(&self, value: Value) -> Option<i64> {
+    pub fn eval(&self, value: azuki_tac::Value) -> Option<Value> {
         match value {
-            Value::Dest(d) => self.vars.get(&d).cloned(),
-            Value::Imm(i) => Some(i),
+            azuki_tac::Value::Dest(d) => self.vars.get(&d).cloned(),
+            azuki_tac::Value::Imm(i) => Some(Value::Int(i)),
+            azuki_tac::Value::FloatImm(f) => Some(Value::Float(f.get())),
         }
     }
 
+    fn eval_ptr(&self, value: azuki_tac::Value) -> Option<usize> {
+        self.eval(value)
+            .and_then(Value::as_int)
+            .and_then(|ptr| usize::try_from(ptr).ok())
+    }
+
     pub fn move_to(&mut self, bb: BBId) {
         self.bb = bb;
         self.instruction = self.func.bb_get(bb).head.into();
@@ -59,7 +101,7 @@ impl<'f> Frame<'f> {
     }
 
     /// Get a reference to the frame's vars.
-    pub fn vars(&self) -> &HashMap<InstId, i64> {
+    pub fn vars(&self) -> &HashMap<InstId, Value> {
         &self.vars
     }
 }
@@ -80,24 +122,73 @@ impl From<Option<InstId>> for CurrInst {
 
 enum JumpAction {
     Goto(BBId),
-    Return(Option<Value>),
+    Return(Option<azuki_tac::Value>),
     Error,
 }
 
 impl<'src> Vm<'src> {
     pub fn new(program: &'src Program) -> Vm<'src> {
+        let mut memory = Vec::new();
+        let mut data = HashMap::new();
+        let mut allocations = Vec::new();
+        for (name, s) in &program.data {
+            let base = memory.len();
+            data.insert(name.clone(), base);
+            memory.extend(s.chars().map(|ch| Value::Int(ch as i64)));
+            memory.push(Value::Int(0));
+            allocations.push((base, memory.len() - base));
+        }
+
         Vm {
             program,
+            externs: HashMap::new(),
             stack: Vec::new(),
+            globals: program
+                .globals
+                .iter()
+                .map(|(name, var)| (name.clone(), Value::Int(var.init)))
+                .collect(),
+            data_end: memory.len(),
+            memory,
+            allocations,
+            data,
+            input: Input::new(std::io::stdin()),
+            output: Box::new(std::io::stdout()),
             inspectors: Vec::new(),
         }
     }
 
+    /// Sets where the input of the program is read from. Defaults to the
+    /// standard input.
+    pub fn set_input(&mut self, input: impl Read + 'src) {
+        self.input = Input::new(input);
+    }
+
+    /// Sets where the output of the program is written into. Defaults to the
+    /// standard output.
+    pub fn set_output(&mut self, output: impl Write + 'src) {
+        self.output = Box::new(output);
+    }
+
+    /// Provides the implementation of the extern function `name`. Calling an
+    /// extern function that isn't provided stops the program with
+    /// [`VmError::MissingExtern`].
+    pub fn add_extern(&mut self, name: &str, func: impl FnMut(&[Value]) -> Option<Value> + 'src) {
+        self.externs.insert(name.into(), Box::new(func));
+    }
+
+    /// Get a reference to the current values of global variables.
+    pub fn globals(&self) -> &HashMap<SmolStr, Value> {
+        &self.globals
+    }
+
     pub fn add_inspector_boxed(&mut self, inspector: Rc<RefCell<dyn Inspector>>) {
         self.inspectors.push(inspector);
     }
 
-    pub fn run_func(&mut self, name: &str, params: Vec<i64>) -> Option<i64> {
+    /// Runs the function `name` with the given parameters, returning `None` if
+    /// the function returns nothing.
+    pub fn run_func(&mut self, name: &str, params: Vec<Value>) -> Result<Option<Value>, VmError> {
         let func = self
             .program
             .functions
@@ -118,35 +209,42 @@ impl<'src> Vm<'src> {
             vars: HashMap::new(),
             last_bb: BBId::default(),
             bb: func.starting_block().unwrap(),
+            mem_base: self.memory.len(),
         });
 
-        let ret = self.run_till_return()?;
+        // `None` if the function returns nothing
+        let ret = self.run_till_return();
 
-        self.stack.pop();
-        Some(ret)
+        let frame = self.stack.pop().unwrap();
+        self.memory.truncate(frame.mem_base);
+        let kept = self
+            .allocations
+            .partition_point(|&(base, _)| base < frame.mem_base);
+        self.allocations.truncate(kept);
+        ret
     }
 
-    fn run_till_return(&mut self) -> Option<i64> {
+    fn run_till_return(&mut self) -> Result<Option<Value>, VmError> {
         assert!(!self.stack.is_empty());
         loop {
             let last = self.stack.last_mut().unwrap();
             match last.instruction {
                 CurrInst::Instruction(i) => {
                     let next = last.func.tac_get(i).next;
-                    self.run_inst_in_curr_func(i);
+                    self.run_inst_in_curr_func(i)?;
                     let last = self.stack.last_mut().unwrap();
                     last.instruction = next.into();
                 }
                 CurrInst::Jump => {
                     if let Some(value) = self.run_jump_inst() {
-                        return value;
+                        return Ok(value);
                     }
                 }
             }
         }
     }
 
-    fn run_inst_in_curr_func(&mut self, idx: InstId) {
+    fn run_inst_in_curr_func(&mut self, idx: InstId) -> Result<(), VmError> {
         assert!(!self.stack.is_empty());
 
         let last = self.stack.last().unwrap();
@@ -166,7 +264,21 @@ impl<'src> Vm<'src> {
                     .collect::<Option<Vec<_>>>()
                     .unwrap();
 
-                self.run_func(&func.name, params)
+                if self.program.externs.contains_key(&func.name) {
+                    self.run_extern(&func.name, &params)?
+                } else {
+                    self.run_func(&func.name, params)?
+                }
+            }
+            azuki_tac::InstKind::Intrinsic(call) => {
+                let params = call
+                    .params
+                    .iter()
+                    .map(|x| last.eval(*x))
+                    .collect::<Option<Vec<_>>>()
+                    .unwrap();
+
+                self.run_intrinsic(call, &params)?
             }
             azuki_tac::InstKind::Assign(v) => last.eval(*v),
             azuki_tac::InstKind::Phi(sources) => {
@@ -174,21 +286,196 @@ impl<'src> Vm<'src> {
                 sources.get(&last_bb).and_then(|&val| last.eval(val.into()))
             }
             azuki_tac::InstKind::Param(i) => last.params.get(*i).cloned(),
+            azuki_tac::InstKind::LoadGlobal(name) => self.globals.get(name).cloned(),
+            azuki_tac::InstKind::StoreGlobal(name, v) => {
+                let val = last.eval(*v);
+                if let Some(val) = val {
+                    self.globals.insert(name.clone(), val);
+                }
+                val
+            }
+            azuki_tac::InstKind::Addr(name) => {
+                self.data.get(name).map(|&ptr| Value::Int(ptr as i64))
+            }
+            azuki_tac::InstKind::Alloca(count) => {
+                // New memory holds zeros of the element type
+                let zero = match inst.inst.ty.elem() {
+                    Some(elem) if *elem == Ty::float() => Value::Float(0.0),
+                    _ => Value::Int(0),
+                };
+                let ptr = self.memory.len();
+                self.memory.resize(ptr + count, zero);
+                self.allocations.push((ptr, *count));
+                Some(Value::Int(ptr as i64))
+            }
+            azuki_tac::InstKind::Load(ptr) => match last.eval_ptr(*ptr) {
+                Some(ptr) => {
+                    self.check_access(ptr)?;
+                    self.memory.get(ptr).cloned()
+                }
+                None => None,
+            },
+            azuki_tac::InstKind::Store { ptr, val } => {
+                let ptr = last.eval_ptr(*ptr);
+                if let Some(ptr) = ptr {
+                    self.check_access(ptr)?;
+                    if ptr < self.data_end {
+                        return Err(VmError::WriteToReadOnly { ptr });
+                    }
+                }
+                let val = last.eval(*val);
+                match (ptr, val) {
+                    (Some(ptr), Some(val)) => {
+                        self.memory[ptr] = val;
+                        Some(val)
+                    }
+                    _ => None,
+                }
+            }
+            azuki_tac::InstKind::PtrOffset { ptr, offset } => {
+                let ptr = last.eval(*ptr).and_then(Value::as_int);
+                let offset = last.eval(*offset).and_then(Value::as_int);
+                match ptr.zip(offset) {
+                    Some((ptr, offset)) => Some(Value::Int(self.offset_ptr(ptr, offset)?)),
+                    None => None,
+                }
+            }
+            azuki_tac::InstKind::IntToFloat(v) => last
+                .eval(*v)
+                .and_then(Value::as_int)
+                .map(|i| Value::Float(i as f64)),
+            azuki_tac::InstKind::FloatToInt(v) => last
+                .eval(*v)
+                .and_then(Value::as_float)
+                .map(|f| Value::Int(f as i64)),
             azuki_tac::InstKind::Dead => None,
         };
 
         let last = self.stack.last_mut().unwrap();
-        last.vars.insert(idx, res.unwrap());
+        match res {
+            Some(res) => {
+                last.vars.insert(idx, res);
+            }
+            // Values of unit type are never read
+            None if last.func.tac_get(idx).inst.ty == Ty::Unit => {}
+            None => panic!("Instruction did not produce a value"),
+        }
+        Ok(())
+    }
+
+    /// Returns the base address and length of the allocation containing `ptr`.
+    fn allocation_of(&self, ptr: usize) -> Option<(usize, usize)> {
+        let idx = self.allocations.partition_point(|&(base, _)| base <= ptr);
+        let (base, len) = *self.allocations.get(idx.checked_sub(1)?)?;
+        (ptr < base + len).then_some((base, len))
+    }
+
+    /// Checks that `ptr` points into an allocation before reading or writing it.
+    fn check_access(&self, ptr: usize) -> Result<(), VmError> {
+        match self.allocation_of(ptr) {
+            Some(_) => Ok(()),
+            None => Err(VmError::OutOfBounds { ptr: ptr as i64 }),
+        }
+    }
+
+    /// Moves `ptr` by `offset` elements, which must stay inside the allocation
+    /// `ptr` points into.
+    fn offset_ptr(&self, ptr: i64, offset: i64) -> Result<i64, VmError> {
+        let res = ptr
+            .checked_add(offset)
+            .ok_or(VmError::OutOfBounds { ptr })?;
+        let (base, len) = usize::try_from(ptr)
+            .ok()
+            .and_then(|ptr| self.allocation_of(ptr))
+            .ok_or(VmError::OutOfBounds { ptr })?;
+        match usize::try_from(res) {
+            Ok(res) if base <= res && res < base + len => Ok(res as i64),
+            _ => Err(VmError::OutOfBounds { ptr: res }),
+        }
     }
 
-    fn run_binary_inst(&self, frame: &Frame, inst: &BinaryInst) -> Option<i64> {
+    /// Runs a function provided by the host.
+    fn run_extern(&mut self, name: &SmolStr, params: &[Value]) -> Result<Option<Value>, VmError> {
+        let func = self
+            .externs
+            .get_mut(name)
+            .ok_or_else(|| VmError::MissingExtern(name.clone()))?;
+        Ok(func(params))
+    }
+
+    /// Runs a function provided by the runtime.
+    fn run_intrinsic(
+        &mut self,
+        call: &IntrinsicCall,
+        params: &[Value],
+    ) -> Result<Option<Value>, VmError> {
+        let int_param = || params.first().and_then(|p| p.as_int());
+        match call.intrinsic {
+            Intrinsic::GetInt => {
+                let val = self.input.read_int().ok_or(VmError::InvalidInput)?;
+                return Ok(Some(Value::Int(val)));
+            }
+            Intrinsic::GetChar => {
+                let val = self.input.next_byte().map_or(-1, |b| b as i64);
+                return Ok(Some(Value::Int(val)));
+            }
+            Intrinsic::PutInt => {
+                if let Some(val) = int_param() {
+                    self.write_output(&val.to_string());
+                }
+            }
+            Intrinsic::PutChar => {
+                if let Some(ch) = int_param() {
+                    self.write_output(&char_of(ch).to_string());
+                }
+            }
+            Intrinsic::PutStr => {
+                let ptr = int_param().and_then(|ptr| usize::try_from(ptr).ok());
+                if let Some(mut ptr) = ptr {
+                    let mut s = String::new();
+                    while let Some(&Value::Int(ch)) = self.memory.get(ptr) {
+                        if ch == 0 {
+                            break;
+                        }
+                        s.push(char_of(ch));
+                        ptr += 1;
+                    }
+                    self.write_output(&s);
+                }
+            }
+            Intrinsic::PutLn => self.write_output("\n"),
+        }
+        Ok(None)
+    }
+
+    fn write_output(&mut self, s: &str) {
+        self.output
+            .write_all(s.as_bytes())
+            .expect("Failed to write output");
+    }
+
+    fn run_binary_inst(&self, frame: &Frame, inst: &BinaryInst) -> Option<Value> {
         let lhs = frame.eval(inst.lhs)?;
         let rhs = frame.eval(inst.rhs)?;
+        match (lhs, rhs) {
+            (Value::Int(lhs), Value::Int(rhs)) => Self::run_int_binary_inst(inst, lhs, rhs),
+            (Value::Float(lhs), Value::Float(rhs)) => Self::run_float_binary_inst(inst, lhs, rhs),
+            _ => None,
+        }
+    }
+
+    fn run_int_binary_inst(inst: &BinaryInst, lhs: i64, rhs: i64) -> Option<Value> {
         let res = match inst.op {
             azuki_tac::BinaryOp::Add => lhs + rhs,
             azuki_tac::BinaryOp::Sub => lhs - rhs,
             azuki_tac::BinaryOp::Mul => lhs * rhs,
             azuki_tac::BinaryOp::Div => lhs.checked_div(rhs)?,
+            azuki_tac::BinaryOp::Mod => lhs.checked_rem(rhs)?,
+            azuki_tac::BinaryOp::And => lhs & rhs,
+            azuki_tac::BinaryOp::Or => lhs | rhs,
+            azuki_tac::BinaryOp::Xor => lhs ^ rhs,
+            azuki_tac::BinaryOp::Shl => lhs.checked_shl(u32::try_from(rhs).ok()?)?,
+            azuki_tac::BinaryOp::Shr => lhs.checked_shr(u32::try_from(rhs).ok()?)?,
             azuki_tac::BinaryOp::Lt => (lhs < rhs) as i64,
             azuki_tac::BinaryOp::Gt => (lhs > rhs) as i64,
             azuki_tac::BinaryOp::Le => (lhs <= rhs) as i64,
@@ -196,10 +483,28 @@ impl<'src> Vm<'src> {
             azuki_tac::BinaryOp::Eq => (lhs == rhs) as i64,
             azuki_tac::BinaryOp::Ne => (lhs != rhs) as i64,
         };
+        Some(Value::Int(res))
+    }
+
+    fn run_float_binary_inst(inst: &BinaryInst, lhs: f64, rhs: f64) -> Option<Value> {
+        let res = match inst.op {
+            azuki_tac::BinaryOp::Add => Value::Float(lhs + rhs),
+            azuki_tac::BinaryOp::Sub => Value::Float(lhs - rhs),
+            azuki_tac::BinaryOp::Mul => Value::Float(lhs * rhs),
+            azuki_tac::BinaryOp::Div => Value::Float(lhs / rhs),
+            azuki_tac::BinaryOp::Lt => Value::Int((lhs < rhs) as i64),
+            azuki_tac::BinaryOp::Gt => Value::Int((lhs > rhs) as i64),
+            azuki_tac::BinaryOp::Le => Value::Int((lhs <= rhs) as i64),
+            azuki_tac::BinaryOp::Ge => Value::Int((lhs >= rhs) as i64),
+            azuki_tac::BinaryOp::Eq => Value::Int((lhs == rhs) as i64),
+            azuki_tac::BinaryOp::Ne => Value::Int((lhs != rhs) as i64),
+            // Bitwise operations and remainders are only defined on integers
+            _ => return None,
+        };
         Some(res)
     }
 
-    fn run_jump_inst(&mut self) -> Option<Option<i64>> {
+    fn run_jump_inst(&mut self) -> Option<Option<Value>> {
         let last = self.stack.last_mut().unwrap();
 
         last.last_bb = last.bb;
@@ -219,7 +524,7 @@ impl<'src> Vm<'src> {
                     break;
                 }
                 azuki_tac::Branch::CondJump { cond, target } => {
-                    if last.eval(*cond).map_or(false, |x| x != 0) {
+                    if last.eval(*cond).map_or(false, |x| x != Value::Int(0)) {
                         action = JumpAction::Goto(*target);
                         break;
                     }
@@ -241,3 +546,11 @@ impl<'src> Vm<'src> {
         None
     }
 }
+
+/// Converts an integer into a character, replacing invalid ones with U+FFFD.
+fn char_of(ch: i64) -> char {
+    u32::try_from(ch)
+        .ok()
+        .and_then(std::char::from_u32)
+        .unwrap_or(std::char::REPLACEMENT_CHARACTER)
+}
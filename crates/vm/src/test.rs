@@ -1,5 +1,5 @@
 #![cfg(test)]
-use crate::Vm;
+use crate::{err::VmError, value::Value, Vm};
 
 use azuki_tac::parser::parse_program_from_string;
 
@@ -25,8 +25,8 @@ fn run_fib() {
     ";
     let result = parse_program_from_string(input).unwrap();
     let mut vm = Vm::new(&result);
-    let run_fib = vm.run_func("fib", vec![5]);
-    assert_eq!(run_fib, Some(8));
+    let run_fib = vm.run_func("fib", vec![Value::Int(5)]).unwrap();
+    assert_eq!(run_fib, Some(Value::Int(8)));
 }
 
 #[test]
@@ -43,6 +43,550 @@ fn run_a_plus_b() {
     let result = parse_program_from_string(input).unwrap();
 
     let mut vm = Vm::new(&result);
-    let run_fib = vm.run_func("add", vec![1, 2]);
-    assert_eq!(run_fib, Some(3));
+    let run_fib = vm
+        .run_func("add", vec![Value::Int(1), Value::Int(2)])
+        .unwrap();
+    assert_eq!(run_fib, Some(Value::Int(3)));
+}
+
+fn compile_and_run_values(src: &str, func: &str, params: Vec<Value>) -> Option<Value> {
+    let program = azuki_syntax::parse(src).unwrap();
+    let program = azuki_tacgen::compile(&program).unwrap();
+    let mut vm = Vm::new(&program);
+    vm.run_func(func, params).unwrap()
+}
+
+/// Runs a function that only takes and returns integers.
+fn compile_and_run(src: &str, func: &str, params: Vec<i64>) -> Option<i64> {
+    let params = params.into_iter().map(Value::Int).collect();
+    compile_and_run_values(src, func, params).map(|v| v.as_int().unwrap())
+}
+
+#[test]
+fn run_while_loop() {
+    let input = r"
+    fn sum(n: int) -> int {
+        let i: int = 0;
+        let s: int = 0;
+        while i < n {
+            i = i + 1;
+            s = s + i;
+        }
+        return s;
+    }
+    ";
+    assert_eq!(compile_and_run(input, "sum", vec![10]), Some(55));
+}
+
+#[test]
+fn run_logical_operators() {
+    let input = r"
+    fn test(a: int, b: int) -> int {
+        return (a > 0 && b > 0 || !(a != 0)) as int;
+    }
+    ";
+    assert_eq!(compile_and_run(input, "test", vec![1, 1]), Some(1));
+    assert_eq!(compile_and_run(input, "test", vec![1, 0]), Some(0));
+    assert_eq!(compile_and_run(input, "test", vec![0, 0]), Some(1));
+    assert_eq!(compile_and_run(input, "test", vec![-1, 5]), Some(0));
+}
+
+#[test]
+fn run_short_circuit() {
+    // `fail` divides by zero, so it must never be evaluated
+    let input = r"
+    fn fail(n: int) -> int {
+        return n / 0;
+    }
+    fn test(a: int) -> int {
+        let r: int = 0;
+        if a == 0 || fail(a) > 0 {
+            r = r + 1;
+        }
+        if a != 0 && fail(a) > 0 {
+            r = r + 1;
+        }
+        while a != 0 && fail(a) as bool {
+            r = r + 1;
+        }
+        return r;
+    }
+    ";
+    assert_eq!(compile_and_run(input, "test", vec![0]), Some(1));
+}
+
+#[test]
+fn run_bitwise_operators() {
+    let input = r"
+    fn test(a: int, b: int) -> int {
+        return (a % b) + (a & b) * 10 + (a | b) * 100 + (a ^ b) * 1000;
+    }
+    fn shift(a: int) -> int {
+        return ~(1 << a) & 255 | a >> 1;
+    }
+    ";
+    // 12 = 0b1100, 10 = 0b1010
+    assert_eq!(
+        compile_and_run(input, "test", vec![12, 10]),
+        Some(2 + 8 * 10 + 14 * 100 + 6 * 1000)
+    );
+    assert_eq!(
+        compile_and_run(input, "shift", vec![3]),
+        Some(0b1111_0111 | 1)
+    );
+}
+
+#[test]
+fn run_bitwise_tac() {
+    let input = r"
+    fn @parity(i32) -> i32 {
+    bb0:
+        %0 = i32 param 0
+        %1 = i32 shr %0 #1
+        %2 = i32 xor %0 %1
+        %3 = i32 and %2 #1
+        return %3
+    }
+    ";
+    let result = parse_program_from_string(input).unwrap();
+    let mut vm = Vm::new(&result);
+    assert_eq!(
+        vm.run_func("parity", vec![Value::Int(0b10)]).unwrap(),
+        Some(Value::Int(1))
+    );
+    assert_eq!(
+        vm.run_func("parity", vec![Value::Int(0b11)]).unwrap(),
+        Some(Value::Int(0))
+    );
+}
+
+#[test]
+fn run_for_loop() {
+    let input = r"
+    fn sum_odd(n: int) -> int {
+        let s: int = 0;
+        for (let i: int = 0; i < n; i = i + 1) {
+            if i % 2 == 0 {
+                continue;
+            }
+            s = s + i;
+        }
+        return s;
+    }
+    fn first_square_above(n: int) -> int {
+        let i: int = 0;
+        for (;; i = i + 1) {
+            if i * i > n {
+                break;
+            }
+        }
+        return i;
+    }
+    ";
+    assert_eq!(compile_and_run(input, "sum_odd", vec![10]), Some(25));
+    assert_eq!(
+        compile_and_run(input, "first_square_above", vec![50]),
+        Some(8)
+    );
+}
+
+#[test]
+fn run_globals() {
+    let input = r"
+    const base: int = 10;
+    let counter: int = base * 2 + 1;
+    let calls: int;
+    fn bump(n: int) -> int {
+        counter = counter + n;
+        calls = calls + 1;
+        return counter;
+    }
+    fn main() -> int {
+        bump(1);
+        return bump(base);
+    }
+    ";
+    let program = azuki_syntax::parse(input).unwrap();
+    let program = azuki_tacgen::compile(&program).unwrap();
+
+    // Globals should survive a round trip through the text format
+    let program = parse_program_from_string(&program.to_string()).unwrap();
+    assert_eq!(program.globals["counter"].init, 21);
+
+    let mut vm = Vm::new(&program);
+    assert_eq!(vm.run_func("main", vec![]).unwrap(), Some(Value::Int(32)));
+    assert_eq!(vm.globals()["counter"], Value::Int(32));
+    assert_eq!(vm.globals()["calls"], Value::Int(2));
+}
+
+#[test]
+fn run_arrays() {
+    let input = r"
+    fn sort(a: [int; 5], n: int) -> void {
+        for (let i: int = 0; i < n; i = i + 1) {
+            for (let j: int = 0; j + 1 < n - i; j = j + 1) {
+                if a[j] > a[j + 1] {
+                    let t: int = a[j];
+                    a[j] = a[j + 1];
+                    a[j + 1] = t;
+                }
+            }
+        }
+        return;
+    }
+    fn main() -> int {
+        let a: [int; 5];
+        a[0] = 4;
+        a[1] = 1;
+        a[2] = 5;
+        a[3] = 3;
+        a[4] = 2;
+        sort(a, 5);
+        let res: int = 0;
+        for (let i: int = 0; i < 5; i = i + 1) {
+            res = res * 10 + a[i];
+        }
+        return res;
+    }
+    ";
+    assert_eq!(compile_and_run(input, "main", vec![]), Some(12345));
+}
+
+#[test]
+fn run_memory_tac() {
+    let input = r"
+    fn @main() -> i32 {
+    bb0:
+        %0 = i32* alloca 2
+        %1 = i32* offset %0 #1
+        %2 = unit store %1 #42
+        %3 = i32 load %1
+        return %3
+    }
+    ";
+    let result = parse_program_from_string(input).unwrap();
+    let mut vm = Vm::new(&result);
+    assert_eq!(vm.run_func("main", vec![]).unwrap(), Some(Value::Int(42)));
+}
+
+#[test]
+fn run_out_of_bounds() {
+    let input = r"
+    fn main(i: int) -> int {
+        let a: [int; 2];
+        let b: [int; 2];
+        a[0] = 1;
+        b[i] = 42;
+        return a[0] + b[i];
+    }
+    ";
+    let program = azuki_syntax::parse(input).unwrap();
+    let program = azuki_tacgen::compile(&program).unwrap();
+    let mut vm = Vm::new(&program);
+    assert_eq!(
+        vm.run_func("main", vec![Value::Int(1)]),
+        Ok(Some(Value::Int(43)))
+    );
+    let mut vm = Vm::new(&program);
+    assert!(matches!(
+        vm.run_func("main", vec![Value::Int(-2)]),
+        Err(VmError::OutOfBounds { .. })
+    ));
+    let mut vm = Vm::new(&program);
+    assert!(matches!(
+        vm.run_func("main", vec![Value::Int(2)]),
+        Err(VmError::OutOfBounds { .. })
+    ));
+}
+
+#[test]
+fn run_bool() {
+    let input = r"
+    fn is_even(n: int) -> bool {
+        return n % 2 == 0;
+    }
+    fn count_even(n: int) -> int {
+        let res: int = 0;
+        let flag: bool = false;
+        for (let i: int = 0; i < n; i = i + 1) {
+            flag = is_even(i);
+            res = res + flag as int;
+        }
+        return res;
+    }
+    ";
+    assert_eq!(compile_and_run(input, "count_even", vec![7]), Some(4));
+}
+
+#[test]
+fn run_float() {
+    let input = r"
+    fn sqrt(x: double) -> double {
+        let r: double = x;
+        for (let i: int = 0; i < 20; i = i + 1) {
+            r = (r + x / r) / 2.0;
+        }
+        return r;
+    }
+    fn mean(a: int, b: int) -> double {
+        return (a + b) as double / 2.0;
+    }
+    fn floor_neg(x: double) -> int {
+        return -x as int;
+    }
+    ";
+    let sqrt = compile_and_run_values(input, "sqrt", vec![Value::Float(2.0)]).unwrap();
+    assert!((sqrt.as_float().unwrap() - 2f64.sqrt()).abs() < 1e-9);
+    assert_eq!(
+        compile_and_run_values(input, "mean", vec![Value::Int(1), Value::Int(2)]),
+        Some(Value::Float(1.5))
+    );
+    assert_eq!(
+        compile_and_run_values(input, "floor_neg", vec![Value::Float(2.5)]),
+        Some(Value::Int(-2))
+    );
+}
+
+#[test]
+fn run_float_array() {
+    // Unwritten elements of a double array are zeros of type double
+    let input = r"
+    fn main() -> double {
+        let a: [double; 3];
+        a[0] = 1.5;
+        return a[0] + a[1] + (a[2] as int) as double;
+    }
+    ";
+    let result = compile_and_run_values(input, "main", vec![]);
+    assert_eq!(result, Some(Value::Float(1.5)));
+}
+
+#[test]
+fn run_float_tac() {
+    let input = r"
+    fn @main() -> i32 {
+    bb0:
+        %0 = f64 itof #3
+        %1 = f64 mul %0 #1.5
+        %2 = b32 gt %1 #4.0
+        %3 = i32 ftoi %1
+        br bb1 if %2
+        br bb2
+    bb1:
+        return %3
+    bb2:
+        return #0
+    }
+    ";
+    let result = parse_program_from_string(input).unwrap();
+    // Float immediates should survive a round trip through the text format
+    let result = parse_program_from_string(&result.to_string()).unwrap();
+    let mut vm = Vm::new(&result);
+    assert_eq!(vm.run_func("main", vec![]).unwrap(), Some(Value::Int(4)));
+}
+
+#[test]
+fn run_putstr() {
+    let input = r#"
+    fn len(s: [int; 6]) -> int {
+        let i: int = 0;
+        while s[i] != 0 {
+            i = i + 1;
+        }
+        return i;
+    }
+    fn main() -> int {
+        putstr("Hello, world!\n");
+        putstr("\"quoted\"\ttab\n");
+        putstr("Hello, world!\n");
+        return len("Hello");
+    }
+    "#;
+    let program = azuki_syntax::parse(input).unwrap();
+    let program = azuki_tacgen::compile(&program).unwrap();
+    // Identical strings share the same data
+    assert_eq!(program.data.len(), 3);
+
+    // Data should survive a round trip through the text format
+    let program = parse_program_from_string(&program.to_string()).unwrap();
+
+    let mut output = Vec::new();
+    let mut vm = Vm::new(&program);
+    vm.set_output(&mut output);
+    assert_eq!(vm.run_func("main", vec![]).unwrap(), Some(Value::Int(5)));
+    drop(vm);
+    assert_eq!(
+        String::from_utf8(output).unwrap(),
+        "Hello, world!\n\"quoted\"\ttab\nHello, world!\n"
+    );
+}
+
+#[test]
+fn run_write_to_string() {
+    // Writes through a pointer to a string literal are only caught at runtime
+    let input = r#"
+    fn set(s: [int; 4]) -> void { s[0] = 1; }
+    fn main() -> void { set("abc"); }
+    "#;
+    let program = azuki_syntax::parse(input).unwrap();
+    let program = azuki_tacgen::compile(&program).unwrap();
+    let mut vm = Vm::new(&program);
+    assert!(matches!(
+        vm.run_func("main", vec![]),
+        Err(VmError::WriteToReadOnly { .. })
+    ));
+}
+
+#[test]
+fn run_io() {
+    let input = r"
+    fn main() -> void {
+        let n: int = getint();
+        let s: int = 0;
+        for (let i: int = 0; i < n; i = i + 1) {
+            s = s + getint();
+        }
+        putint(s);
+        putln();
+        let ch: int = getchar();
+        while ch != -1 {
+            if ch != ' ' {
+                putchar(ch);
+            }
+            ch = getchar();
+        }
+        return;
+    }
+    ";
+    let program = azuki_syntax::parse(input).unwrap();
+    let program = azuki_tacgen::compile(&program).unwrap();
+    // Intrinsics should survive a round trip through the text format
+    let program = parse_program_from_string(&program.to_string()).unwrap();
+
+    let mut output = Vec::new();
+    let mut vm = Vm::new(&program);
+    vm.set_input("3\n  10 -2\t+5 a b c".as_bytes());
+    vm.set_output(&mut output);
+    vm.run_func("main", vec![]).unwrap();
+    drop(vm);
+    assert_eq!(String::from_utf8(output).unwrap(), "13\nabc");
+
+    // Reading an integer from input that doesn't start with one is an error
+    let mut vm = Vm::new(&program);
+    vm.set_input("abc".as_bytes());
+    vm.set_output(std::io::sink());
+    assert_eq!(vm.run_func("main", vec![]), Err(VmError::InvalidInput));
+}
+
+#[test]
+fn run_mutual_recursion() {
+    let input = r"
+    fn main(n: int) -> int {
+        return is_even(n) as int * 10 + is_odd(n) as int;
+    }
+    fn is_even(n: int) -> bool {
+        if n == 0 {
+            return true;
+        }
+        return is_odd(n - 1);
+    }
+    fn is_odd(n: int) -> bool {
+        if n == 0 {
+            return false;
+        }
+        return is_even(n - 1);
+    }
+    ";
+    assert_eq!(compile_and_run(input, "main", vec![4]), Some(10));
+    assert_eq!(compile_and_run(input, "main", vec![7]), Some(1));
+}
+
+#[test]
+fn run_extern_funcs() {
+    let input = r"
+    extern fn record(x: int) -> void;
+    extern fn scale(x: double) -> double;
+    fn main(n: int) -> double {
+        for (let i: int = 0; i < n; i = i + 1) {
+            record(i * i);
+        }
+        return scale(1.5);
+    }
+    ";
+    let program = azuki_syntax::parse(input).unwrap();
+    let program = azuki_tacgen::compile(&program).unwrap();
+    // Externs should survive a round trip through the text format
+    let program = parse_program_from_string(&program.to_string()).unwrap();
+    assert_eq!(program.externs.len(), 2);
+
+    let mut recorded = vec![];
+    let mut vm = Vm::new(&program);
+    vm.add_extern("record", |params| {
+        recorded.push(params[0]);
+        None
+    });
+    vm.add_extern("scale", |params| {
+        Some(Value::Float(params[0].as_float()? * 2.0))
+    });
+    assert_eq!(
+        vm.run_func("main", vec![Value::Int(4)]).unwrap(),
+        Some(Value::Float(3.0))
+    );
+    drop(vm);
+    assert_eq!(
+        recorded,
+        vec![Value::Int(0), Value::Int(1), Value::Int(4), Value::Int(9)]
+    );
+}
+
+#[test]
+fn run_missing_extern() {
+    let input = r"
+    extern fn missing() -> void;
+    fn main() -> void { missing(); }
+    ";
+    let program = azuki_syntax::parse(input).unwrap();
+    let program = azuki_tacgen::compile(&program).unwrap();
+    let mut vm = Vm::new(&program);
+    assert_eq!(
+        vm.run_func("main", vec![]),
+        Err(VmError::MissingExtern("missing".into()))
+    );
+}
+
+#[test]
+fn run_compound_assign_and_inc_dec() {
+    let input = r"
+    let g: int = 1;
+    fn main(n: int) -> int {
+        let a: [int; 4];
+        let s: int = 0;
+        for (let i: int = 0; i < 4; i++) {
+            a[i] = i;
+            a[i] *= 10;
+        }
+        let i: int = 0;
+        // The postfix operator evaluates to the old value
+        a[i++] += 1;
+        a[i++] -= 1;
+        s += a[0] + a[1];
+        g++;
+        s %= 100;
+        s += --n * 1000 + g * 100000;
+        return s + ++i;
+    }
+    ";
+    // a = [1, 9, 20, 30], s = 10, n = 4, g = 2, i = 3
+    assert_eq!(compile_and_run(input, "main", vec![5]), Some(204013));
+
+    let input = r"
+    fn main() -> double {
+        let x: double = 1.5;
+        let y: double = x++;
+        x /= 2.0;
+        return x * 10.0 + y;
+    }
+    ";
+    let result = compile_and_run_values(input, "main", vec![]);
+    assert_eq!(result, Some(Value::Float(14.0)));
 }
@@ -1,11 +1,46 @@
-use azuki_tac::InstId;
+use std::fmt::Display;
 
+/// A value inside the virtual machine. Booleans and pointers are represented as
+/// integers.
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum Value {
     Int(i64),
-    StackPointer {
-        frame: u32,
-        value: InstId,
-        offset: u32,
-    },
-    HeapPointer {},
+    Float(f64),
+}
+
+impl Value {
+    pub fn as_int(self) -> Option<i64> {
+        match self {
+            Value::Int(i) => Some(i),
+            _ => None,
+        }
+    }
+
+    pub fn as_float(self) -> Option<f64> {
+        match self {
+            Value::Float(f) => Some(f),
+            _ => None,
+        }
+    }
+}
+
+impl From<i64> for Value {
+    fn from(i: i64) -> Self {
+        Value::Int(i)
+    }
+}
+
+impl From<f64> for Value {
+    fn from(f: f64) -> Self {
+        Value::Float(f)
+    }
+}
+
+impl Display for Value {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Value::Int(i) => write!(f, "{}", i),
+            Value::Float(x) => write!(f, "{:?}", x),
+        }
+    }
 }
@@ -16,23 +16,34 @@ HexNumber: HexDigit+;
 Variable: '%' Number;
 GlobalVariable: '@' Ident;
 NumberLiteral: '#' (('+' | '-')? Number | '0x' HexNumber);
+FloatLiteral:
+	'#' ('+' | '-')? Number '.' Number ([eE] ('+' | '-')? Number)?;
+StringLiteral: '"' (~["\\] | '\\' .)* '"';
 BasicBlock: 'bb' Number;
 DiscardVariable: '%_';
 
 // types
 IntegerType: 'i' Number;
 BooleanType: 'b' Number;
+FloatType: 'f' Number;
 UnitType: 'unit';
 
 // keywords
 FN: 'fn';
 GLOBAL: 'global';
+DATA: 'data';
 
 // Operators
 ADD: 'add';
 SUB: 'sub';
 MUL: 'mul';
 DIV: 'div';
+MOD: 'mod';
+AND: 'and';
+OR: 'or';
+XOR: 'xor';
+SHL: 'shl';
+SHR: 'shr';
 
 GT: 'gt';
 GE: 'ge';
@@ -47,36 +58,84 @@ IF: 'if';
 UNREACHABLE: 'unreachable';
 RETURN: 'return';
 CALL: 'call';
+INTRINSIC: 'intrinsic';
+LOAD: 'load';
+STORE: 'store';
+ALLOCA: 'alloca';
+OFFSET: 'offset';
+ADDR: 'addr';
+ITOF: 'itof';
+FTOI: 'ftoi';
 
 // misc
 LINEFEED: '\n';
 
 // Grammar components
 literal: NumberLiteral;
-value: Variable | GlobalVariable | NumberLiteral;
+value: Variable | GlobalVariable | NumberLiteral | FloatLiteral;
 
 int_ty: IntegerType;
 bool_ty: BooleanType;
+float_ty: FloatType;
 unit_ty: UnitType;
-ptr_ty: '*' ty;
 func_ty: FN function_param '->' ty;
-ty: int_ty | bool_ty | unit_ty | ptr_ty | func_ty;
+ty: int_ty | bool_ty | float_ty | unit_ty | ty '*' | func_ty;
 
 // instructions
-binary_op: ADD | SUB | MUL | DIV | GT | GE | LT | LE | EQ | NE;
+binary_op:
+	ADD
+	| SUB
+	| MUL
+	| DIV
+	| MOD
+	| AND
+	| OR
+	| XOR
+	| SHL
+	| SHR
+	| GT
+	| GE
+	| LT
+	| LE
+	| EQ
+	| NE;
 binary_inst: value binary_op value;
 
 fn_param_list: (value (',' value)*)?;
 fn_call_inst: CALL GlobalVariable '(' fn_param_list ')';
+intrinsic_call_inst: INTRINSIC Ident '(' fn_param_list ')';
 
 phi_source: '(' Variable ',' BasicBlock ')';
 phi_inst: PHI '[' (phi_source (',' phi_source)*)? ']';
 
 val_inst: value;
 
+load_global_inst: LOAD GlobalVariable;
+store_global_inst: STORE GlobalVariable value;
+addr_inst: ADDR GlobalVariable;
+
+alloca_inst: ALLOCA Number;
+load_inst: LOAD value;
+store_inst: STORE value value;
+offset_inst: OFFSET value value;
+conversion_inst: (ITOF | FTOI) value;
+
 variable: Variable;
 inst_lhs: ty variable | DiscardVariable;
-inst_rhs: binary_inst | phi_inst | val_inst | fn_call_inst;
+inst_rhs:
+	binary_inst
+	| phi_inst
+	| val_inst
+	| fn_call_inst
+	| intrinsic_call_inst
+	| load_global_inst
+	| store_global_inst
+	| addr_inst
+	| alloca_inst
+	| load_inst
+	| store_inst
+	| offset_inst
+	| conversion_inst;
 inst: inst_lhs '=' ty inst_rhs LINEFEED;
 
 unreachable_inst: UNREACHABLE;
@@ -100,5 +159,6 @@ function:
 	FN GlobalVariable function_param '->' ty '{' LINEFEED basic_block+ '}' LINEFEED;
 
 // program
-global_var: GLOBAL GlobalVariable '=' literal LINEFEED;
-program: (global_var | function)*;
+global_var: GLOBAL GlobalVariable '=' ty literal LINEFEED;
+data: DATA GlobalVariable '=' StringLiteral LINEFEED;
+program: global_var* data* function*;
@@ -1,7 +1,7 @@
 use std::{cell::RefCell, path::PathBuf, process::exit, rc::Rc};
 
 use azuki_tac::parser::parse_program_from_string;
-use azuki_tacvm::{inspector::Inspector, Vm};
+use azuki_tacvm::{inspector::Inspector, value::Value, Vm};
 use clap::Clap;
 
 #[derive(Clap, Debug)]
@@ -49,7 +49,11 @@ fn main() {
         None
     };
 
-    vm.run_func(&opt.entry_point, opt.entry_params);
+    let params = opt.entry_params.iter().map(|&i| Value::Int(i)).collect();
+    if let Err(e) = vm.run_func(&opt.entry_point, params) {
+        eprintln!("Runtime error: {}", e);
+        exit(3);
+    }
 
     if let Some(inst_cnt) = inst_cnt {
         eprintln!("azvm::instruction_cnt={}", inst_cnt.borrow().0);
@@ -68,7 +72,7 @@ impl Inspector for InstCounter {
 
     fn before_branch(&mut self, _inst: &azuki_tac::Branch, _frame: &azuki_tacvm::Frame) {}
 
-    fn before_call(&mut self, _params: &[i64], _func: &azuki_tac::TacFunc) {}
+    fn before_call(&mut self, _params: &[Value], _func: &azuki_tac::TacFunc) {}
 
     fn before_ret(&mut self, _frame: &azuki_tacvm::Frame) {}
 }
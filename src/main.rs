@@ -1,10 +1,16 @@
 use std::io::{stdout, Write};
 
-use azuki_syntax::{lexer::lexer, parse};
+use azuki_syntax::{
+    ast::formatter::{PrettyPrinter, TreeDump},
+    lexer::{spanned_lexer, SpannedToken},
+    parse_recovering,
+    source_map::SourceMap,
+    Token,
+};
 use azuki_tac::optimizer::sanity_checker::SanityChecker;
-use azuki_tacvm::Vm;
+use azuki_tacvm::{value::Value, Vm};
 use clap::Clap;
-use opt::Action;
+use opt::{Action, OutputFormat};
 
 mod opt;
 
@@ -12,12 +18,18 @@ fn main() {
     let opt = opt::Opt::parse();
 
     let file = opt.file;
-    let input = std::fs::read_to_string(file).expect("Unable to read input file");
+    let input = std::fs::read_to_string(&file).expect("Unable to read input file");
+    let file_name = file.to_string_lossy().into_owned();
+
+    // Formatting rewrites the input file unless an output file is given
+    let fmt_in_place = opt.action == Action::Fmt && opt.out_file.is_none();
 
     let mut output: Box<dyn Write> = match opt.out_file {
         Some(file) => Box::new(
             std::fs::OpenOptions::new()
                 .write(true)
+                .create(true)
+                .truncate(true)
                 .open(file)
                 .expect("Failed to open output file"),
         ),
@@ -26,31 +38,84 @@ fn main() {
 
     if opt.action == Action::Lex {
         // lex file
-        let lexer = lexer(&input);
-        lexer.for_each(|token| {
-            writeln!(output, "{}", token).expect("Failed to write to output file")
-        });
+        match opt.format {
+            OutputFormat::Text => {
+                spanned_lexer(&input).for_each(|token| {
+                    match token {
+                        Ok((token, _)) => writeln!(output, "{}", token),
+                        Err(e) => writeln!(output, "err {}", e.kind),
+                    }
+                    .expect("Failed to write to output file")
+                });
+            }
+            OutputFormat::Json => {
+                let tokens = spanned_lexer(&input)
+                    .map(|token| match token {
+                        Ok((token, span)) => SpannedToken { token, span },
+                        Err(e) => SpannedToken {
+                            token: Token::LexError(e.kind),
+                            span: e.span,
+                        },
+                    })
+                    .collect::<Vec<_>>();
+                serde_json::to_writer_pretty(&mut output, &tokens)
+                    .expect("Failed to write to output file");
+                writeln!(output).expect("Failed to write to output file");
+            }
+            OutputFormat::Tree => eprintln!("Tokens cannot be output as a tree"),
+        }
         return;
     }
 
-    let program = match parse(&input) {
-        Ok(p) => p,
-        Err(e) => {
-            // TODO: Error display
-            println!("{:?}", e);
+    let mut files = SourceMap::new();
+    let main_file = files.add(file_name, input.as_str());
+
+    // Parsing and formatting only look at the input file itself, while
+    // compiling needs all imported files too
+    if opt.action == Action::Parse || opt.action == Action::Fmt {
+        let (program, errors) = parse_recovering(&input);
+        if !errors.is_empty() {
+            for e in &errors {
+                eprint!("{}", files.render(&e.to_diagnostic()));
+            }
             return;
         }
-    };
 
-    if opt.action == Action::Parse {
-        // TODO: output parse result
+        if opt.action == Action::Parse {
+            match opt.format {
+                OutputFormat::Text => write!(output, "{}", program),
+                OutputFormat::Tree => write!(output, "{}", TreeDump(&program)),
+                OutputFormat::Json => serde_json::to_writer_pretty(&mut output, &program)
+                    .map_err(Into::into)
+                    .and_then(|_| writeln!(output)),
+            }
+            .expect("Failed to write to output file");
+        } else {
+            let formatted = PrettyPrinter::default().print_program_with_comments(&program, &input);
+            if fmt_in_place {
+                std::fs::write(&file, formatted).expect("Failed to write to input file");
+            } else {
+                write!(output, "{}", formatted).expect("Failed to write to output file");
+            }
+        }
+        return;
+    }
+
+    let (programs, errors) =
+        files.parse_with_imports(main_file, |path| std::fs::read_to_string(path));
+    if !errors.is_empty() {
+        for e in &errors {
+            eprint!("{}", files.render(&e.to_diagnostic()));
+        }
         return;
     }
 
-    let mut program = match azuki_tacgen::compile(&program) {
+    let mut program = match azuki_tacgen::compile_files(&programs) {
         Ok(p) => p,
-        Err(e) => {
-            eprintln!("{:?}", e);
+        Err(errors) => {
+            for e in &errors {
+                eprint!("{}", files.render(&e.to_diagnostic()));
+            }
             return;
         }
     };
@@ -62,12 +127,27 @@ fn main() {
     pipeline.optimize(&mut program);
 
     if opt.action == Action::Compile {
-        program.functions.iter().for_each(|(_, function)| {
-            writeln!(output, "{}", function).expect("Failed to write to output file");
-            writeln!(output).unwrap();
-        });
+        write!(output, "{}", program).expect("Failed to write to output file");
     } else if opt.action == Action::Run {
-        // TODO: Run program
-        let vm = Vm::new(&program);
+        let entry_point = opt.entry_point.as_deref().unwrap_or("main");
+        if !program.functions.contains_key(entry_point) {
+            eprintln!("Entry point `{}` does not exist", entry_point);
+            return;
+        }
+        // The driver has no host functions to provide
+        if let Some(name) = program.externs.keys().next() {
+            eprintln!("Extern function `{}` is not provided", name);
+            return;
+        }
+        let params = opt.params.iter().map(|&i| Value::Int(i)).collect();
+
+        let mut vm = Vm::new(&program);
+        vm.set_output(&mut output);
+        let res = vm.run_func(entry_point, params);
+        drop(vm);
+        if let Err(e) = res {
+            eprintln!("Runtime error: {}", e);
+        }
+        output.flush().expect("Failed to write to output file");
     }
 }
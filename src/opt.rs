@@ -8,11 +8,12 @@ pub struct Opt {
     /// The file to compile
     pub file: PathBuf,
 
-    /// Output file. Omit for default file (compile) or stdout (other actions).
+    /// Output file. Omit for default file (compile), the input file (fmt) or
+    /// stdout (other actions).
     #[clap(short, long = "out")]
     pub out_file: Option<PathBuf>,
 
-    /// The action to perform. Accepts: lex, parse, compile, run
+    /// The action to perform. Accepts: lex, parse, fmt, compile, run
     #[clap(
         short = 'd',
         long = "do",
@@ -22,6 +23,11 @@ pub struct Opt {
     )]
     pub action: Action,
 
+    /// The output format of lex and parse. Accepts: text, tree (parse only),
+    /// json
+    #[clap(long, default_value = "text")]
+    pub format: OutputFormat,
+
     /// The optimization passes to perform.
     #[clap(long = "opt", env = "AZUKI_OPT")]
     pub optimization: Vec<String>,
@@ -37,6 +43,7 @@ pub struct Opt {
 pub enum Action {
     Lex,
     Parse,
+    Fmt,
     Run,
     Compile,
 }
@@ -48,9 +55,33 @@ impl FromStr for Action {
         Ok(match s {
             "lex" => Self::Lex,
             "parse" => Self::Parse,
+            "fmt" => Self::Fmt,
             "run" => Self::Run,
             "compile" => Self::Compile,
-            _ => return Err(format!("Expected lex, parse, run, got {}", s)),
+            _ => return Err(format!("Expected lex, parse, fmt, compile, run, got {}", s)),
+        })
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum OutputFormat {
+    /// One token per line, or canonically formatted source code
+    Text,
+    /// A tree of syntax nodes and their spans
+    Tree,
+    /// Tokens with spans, or the syntax tree, serialized as JSON
+    Json,
+}
+
+impl FromStr for OutputFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(match s {
+            "text" => Self::Text,
+            "tree" => Self::Tree,
+            "json" => Self::Json,
+            _ => return Err(format!("Expected text, tree, json, got {}", s)),
         })
     }
 }